@@ -0,0 +1,151 @@
+use crate::{curiosities::*, job::JobEvent, position::PositionConvention, structures::*, windows::*};
+
+// Findings past this many are counted, but not sent to the frontend
+pub const MAX_FINDINGS: usize = 10_000;
+
+// Something noteworthy an analysis found in the digits
+#[derive(Clone, Debug, PartialEq)]
+pub struct Finding {
+    pub label: String,
+    pub position: usize, // offset of the first digit
+    pub digits: String,
+}
+
+// Looks at the digit stream in order, one chunk at a time. Anything that may span chunks has to
+// be remembered by the analyzer itself.
+pub trait Analyzer: Send {
+    // `chunk` starts at offset `start`, right where the previous one ended
+    fn feed(&mut self, chunk: &str, start: usize, findings: &mut Vec<Finding>);
+
+    // The stream ended at `end`, for findings still open
+    fn finish(&mut self, _end: usize, _findings: &mut Vec<Finding>) {}
+
+    // Queries that want a first match stop the job once they have it
+    fn done(&self) -> bool {
+        false
+    }
+}
+
+// Runs several analyzers over the same stream
+impl Analyzer for Vec<Box<dyn Analyzer>> {
+    fn feed(&mut self, chunk: &str, start: usize, findings: &mut Vec<Finding>) {
+        for analyzer in self.iter_mut() {
+            analyzer.feed(chunk, start, findings);
+        }
+    }
+
+    fn finish(&mut self, end: usize, findings: &mut Vec<Finding>) {
+        for analyzer in self.iter_mut() {
+            analyzer.finish(end, findings);
+        }
+    }
+
+    fn done(&self) -> bool {
+        !self.is_empty() && self.iter().all(|analyzer| analyzer.done())
+    }
+}
+
+// What an analysis job looks for, turned into an `Analyzer` when the job starts
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+pub enum Analysis {
+    Curiosities(CuriosityOptions),
+    Structures(StructureOptions),
+    Window(WindowQuery),
+}
+
+impl Analysis {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Analysis::Curiosities(_) => "curiosities",
+            Analysis::Structures(_) => "structures",
+            Analysis::Window(_) => "window",
+        }
+    }
+
+    pub fn analyzer(&self, integer_digits: usize) -> Box<dyn Analyzer> {
+        match self {
+            Analysis::Curiosities(options) => Box::new(options.analyzers(integer_digits)),
+            Analysis::Structures(options) => Box::new(options.analyzers()),
+            Analysis::Window(query) => Box::new(WindowSearch::new(*query)),
+        }
+    }
+
+    // Conventions the findings were looked for with, None if they don't depend on one
+    pub fn convention(&self) -> Option<PositionConvention> {
+        match self {
+            Analysis::Curiosities(options) => Some(options.convention),
+            Analysis::Structures(_) | Analysis::Window(_) => None,
+        }
+    }
+
+    // What a query looks for, for job lists
+    pub fn describe(&self) -> Option<String> {
+        match self {
+            Analysis::Window(query) => Some(query.describe()),
+            _ => None,
+        }
+    }
+}
+
+// Sends the new findings, up to MAX_FINDINGS for the whole job.
+// Returns false if the frontend is gone.
+pub fn report_findings(findings: &mut Vec<Finding>, reported: &mut usize, mut send: impl FnMut(JobEvent) -> bool) -> bool {
+    for finding in findings.drain(..) {
+        *reported += 1;
+        if *reported <= MAX_FINDINGS && !send(JobEvent::Finding(finding)) {
+            return false;
+        }
+    }
+    true
+}
+
+pub fn unreported_warning(reported: usize) -> Option<JobEvent> {
+    (reported > MAX_FINDINGS).then(|| JobEvent::Warning(format!("{} more findings weren't listed", reported - MAX_FINDINGS)))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // Feeds the chunks one after the other from `start`, then finishes the stream
+    pub fn run(analyzer: &mut dyn Analyzer, chunks: &[&str], start: usize) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let mut offset = start;
+        for chunk in chunks {
+            analyzer.feed(chunk, offset, &mut findings);
+            offset += chunk.len();
+        }
+        analyzer.finish(offset, &mut findings);
+        findings
+    }
+
+    pub fn positions(findings: &[Finding]) -> Vec<(usize, &str)> {
+        findings.iter().map(|finding| (finding.position, finding.digits.as_str())).collect()
+    }
+
+    #[test]
+    fn report() {
+        let finding = Finding { label: "test".to_string(), position: 0usize, digits: "3".to_string() };
+        let mut findings = vec![finding; MAX_FINDINGS + 2];
+        let (mut reported, mut sent) = (0usize, 0usize);
+        assert!(report_findings(&mut findings, &mut reported, |_| { sent += 1; true }));
+        assert!(findings.is_empty());
+        assert_eq!((reported, sent), (MAX_FINDINGS + 2, MAX_FINDINGS));
+        assert!(matches!(unreported_warning(reported), Some(JobEvent::Warning(_))));
+        assert!(unreported_warning(MAX_FINDINGS).is_none());
+
+        // a closed channel stops the job
+        let mut findings = vec![Finding { label: "test".to_string(), position: 0usize, digits: "3".to_string() }];
+        assert!(!report_findings(&mut findings, &mut 0usize, |_| false));
+    }
+
+    #[test]
+    fn combined() {
+        let none: Vec<Box<dyn Analyzer>> = Vec::new();
+        assert!(!none.done());
+        let mut window: Vec<Box<dyn Analyzer>> = vec![Analysis::Window(WindowQuery { digits: 1, condition: WindowCondition::DigitSum(4) }).analyzer(1)];
+        assert_eq!(positions(&run(&mut window, &["314"], 0)), [(2, "4")]);
+        assert!(window.done());
+    }
+}
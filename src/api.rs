@@ -0,0 +1,103 @@
+// Shared between the blocking and the async clients of api.pi.delivery
+
+use serde::Deserialize;
+use std::fmt;
+
+pub const MAX_DIGITS_PER_REQUEST: usize = 1000;
+
+pub(crate) const MAX_REQUEST_ATTEMPTS: usize = 3;
+
+// {"content":"31415926535"}
+#[derive(Debug, Deserialize)]
+pub struct DigitsResponse {
+    pub content: String,
+}
+
+// {"error":"..."} or {"message":"..."}
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    #[serde(alias = "message")]
+    error: String,
+}
+
+#[derive(Debug)]
+pub enum ApiError {
+    Http { status: u16, message: Option<String> }, // non-2xx status
+    Api(String),                                    // 2xx status with an error payload
+    Json(serde_json::Error),                        // body is neither digits nor an error
+    WrongCount { expected: usize, got: usize },
+    InvalidDigit { position: usize, found: char },  // position inside the response
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Http { status, message: Some(message) } => write!(f, "HTTP {status}: {message}"),
+            ApiError::Http { status, message: None } => write!(f, "HTTP {status}"),
+            ApiError::Api(message) => write!(f, "API error: {message}"),
+            ApiError::Json(err) => write!(f, "Malformed response: {err}"),
+            ApiError::WrongCount { expected, got } => write!(f, "Expected {expected} digits, got {got}"),
+            ApiError::InvalidDigit { position, found } => write!(f, "Invalid digit {found:?} at position {position} of the response"),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ApiError::Json(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+// Turns a response body into exactly `expected` decimal digits
+pub fn parse_digits(status: u16, text: &str, expected: usize) -> Result<String, ApiError> {
+    if !(200..300).contains(&status) {
+        let message = serde_json::from_str::<ErrorResponse>(text).ok().map(|res| res.error);
+        return Err(ApiError::Http { status, message });
+    }
+
+    let response: DigitsResponse = match serde_json::from_str(text) {
+        Ok(response) => response,
+        Err(err) => {
+            return Err(match serde_json::from_str::<ErrorResponse>(text) {
+                Ok(res) => ApiError::Api(res.error),
+                Err(_) => ApiError::Json(err),
+            });
+        },
+    };
+
+    if let Some((position, found)) = response.content.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        return Err(ApiError::InvalidDigit { position, found });
+    }
+    if response.content.len() != expected {
+        return Err(ApiError::WrongCount { expected, got: response.content.len() });
+    }
+
+    Ok(response.content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digits() {
+        assert_eq!(parse_digits(200, r#"{"content":"31415"}"#, 5).unwrap(), "31415");
+        assert_eq!(parse_digits(200, r#"{"content":""}"#, 0).unwrap(), "");
+        assert!(matches!(parse_digits(200, r#"{"content":"3141"}"#, 5), Err(ApiError::WrongCount { expected: 5, got: 4 })));
+        assert!(matches!(parse_digits(200, r#"{"content":"31x15"}"#, 5), Err(ApiError::InvalidDigit { position: 2, found: 'x' })));
+        // digits of other scripts aren't decimal digits here
+        assert!(matches!(parse_digits(200, r#"{"content":"3١415"}"#, 5), Err(ApiError::InvalidDigit { position: 1, .. })));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(parse_digits(404, r#"{"error":"not found"}"#, 5), Err(ApiError::Http { status: 404, message: Some(m) }) if m == "not found"));
+        assert!(matches!(parse_digits(500, "<html>", 5), Err(ApiError::Http { status: 500, message: None })));
+        assert!(matches!(parse_digits(200, r#"{"message":"too many digits"}"#, 5), Err(ApiError::Api(m)) if m == "too many digits"));
+        assert!(matches!(parse_digits(200, "", 5), Err(ApiError::Json(_))));
+        assert!(matches!(parse_digits(200, r#"{"content":31415}"#, 5), Err(ApiError::Json(_))));
+    }
+}
@@ -1,310 +1,1480 @@
-use std::{sync::mpsc::{Receiver, TryRecvError}, fs::File, io::{Read, Write}};
+#[cfg(not(target_arch = "wasm32"))]
+use instant::Instant;
+use std::collections::BTreeSet;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs::File, io::{Read, Seek}};
+#[cfg(target_arch = "wasm32")]
+use std::{cell::RefCell, rc::Rc};
 
 use eframe::{egui::{self, Ui}, epi};
 
-use crate::search::*;
+use crate::{analysis::*, api::MAX_DIGITS_PER_REQUEST, config::*, curiosities::*, estimate::*, structures::StructureOptions, windows::*, history::*, import::*, job::*, jobs::*, plots::PlotPanel, position::*, viewer::DigitViewer};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{cache_format::*, caches::*, file_dialog::*, search::*};
+#[cfg(target_arch = "wasm32")]
+use crate::web::*;
 
-struct InputInfo {}
-
-impl InputInfo {
-    fn new() -> Self {
-        Self {
-        }
-    }
+enum HistoryAction {
+    Rerun(usize),
+    Show(usize),
 }
 
-struct PreloadInfo {
-    preload_size: usize,
-
-    loaded_size_rec: Receiver<usize>,
-    loaded_size: usize,
-
-    result_rec: Receiver<()>,
+enum JobAction {
+    Priority(JobId, i32),
+    Move(JobId, isize),
+    Cancel(JobId),
 }
 
-impl PreloadInfo {
-    fn new(_input_info: &InputInfo, search: &mut Search, preload_size: usize) -> Self {
-        let (loaded_size_rec, result_rec) = search.preload(preload_size, 10);
-        Self {
-            preload_size,
-            loaded_size_rec,
-            loaded_size: 0usize,
-            result_rec,
-        }
-    }
+// The match Find next/previous step from
+struct MatchCursor {
+    pattern: String,
+    index: usize,
+    bounds: SearchBounds, // of the search that found it
+    next_job: Option<JobId>,
+    message: Option<String>,
 }
 
-struct SearchInfo {
-    processed_size_rec: Receiver<usize>,
-    processed_size: usize,
-
-    result_rec: Receiver<Option<usize>>,
+// Editable text form of `SearchConfig`, validated when applied
+struct Settings {
+    base_url: String,
+    verify_tls: bool,
+    ca_bundle: String,
+    proxy: String,
+    connect_timeout: String,
+    read_timeout: String,
+    user_agent: String,
+    workers: String,
+    digits_per_request: String,
+    max_in_flight: String,
+    requests_per_second: String,
+    message: Option<Result<String, String>>,
 }
 
-impl SearchInfo {
-    fn new(_input_info: &InputInfo, search: &mut Search, search_for: &str) -> Self {
-        let (processed_size_rec, result_rec) = search.search(search_for);
+impl Settings {
+    fn new(config: &SearchConfig) -> Self {
+        let timeout = |t: Option<std::time::Duration>| t.map_or(String::default(), |t| t.as_secs_f64().to_string());
         Self {
-            processed_size_rec,
-            processed_size: 0usize,
-            result_rec,
+            base_url: config.base_url.clone(),
+            verify_tls: config.verify_tls,
+            ca_bundle: config.ca_bundle.as_ref().map_or(String::default(), |p| p.display().to_string()),
+            proxy: config.proxy.clone().unwrap_or_default(),
+            connect_timeout: timeout(config.connect_timeout),
+            read_timeout: timeout(config.read_timeout),
+            user_agent: config.user_agent.clone(),
+            workers: config.workers.to_string(),
+            digits_per_request: config.digits_per_request.to_string(),
+            max_in_flight: config.max_in_flight.to_string(),
+            requests_per_second: config.requests_per_second.map_or(String::default(), |rate| rate.to_string()),
+            message: None,
         }
     }
-}
 
-struct FoundInfo {
-    index: Option<usize>,
-    processed: usize,
-}
-
-impl FoundInfo {
-    fn new(search_info: &SearchInfo, index: Option<usize>) -> Self {
-        Self {
-            index,
-            processed: search_info.processed_size,
-        }
+    fn to_config(&self) -> Result<SearchConfig, String> {
+        let mut config = SearchConfig::default();
+        config.set("base_url", self.base_url.trim())?;
+        config.verify_tls = self.verify_tls;
+        config.set("ca_bundle", self.ca_bundle.trim())?;
+        config.set("proxy", self.proxy.trim())?;
+        config.set("connect_timeout", self.connect_timeout.trim())?;
+        config.set("read_timeout", self.read_timeout.trim())?;
+        config.set("user_agent", self.user_agent.trim())?;
+        config.set("workers", self.workers.trim())?;
+        config.set("digits_per_request", self.digits_per_request.trim())?;
+        config.set("max_in_flight", self.max_in_flight.trim())?;
+        config.set("requests_per_second", self.requests_per_second.trim())?;
+        config.validate()?;
+        Ok(config)
     }
 }
 
-enum AppState {
-    Input(InputInfo),
-    Preload(PreloadInfo),
-    Search(SearchInfo),
-    Found(FoundInfo),
-}
-
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "persistence", serde(default))] // if we add new fields, give them default values when deserializing old state
 pub struct TemplateApp {
-    state: AppState,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    jobs: JobManager,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    selected_job: Option<JobId>,
+    job_priority: i32, // for new jobs
+    #[cfg(not(target_arch = "wasm32"))]
+    caches: CacheList,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    file_dialog: Option<FileDialog>,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    saved_file: Option<SavedFile>,
+    #[cfg(not(target_arch = "wasm32"))]
+    autosave_secs: u64, // during preloads, 0 disables
+    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg_attr(feature = "persistence", serde(skip, default = "Instant::now"))]
+    last_autosave: Instant,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    new_cache_name: String,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    files_message: Option<Result<String, String>>,
+    #[cfg(target_arch = "wasm32")]
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    import_message: Rc<RefCell<Option<Result<String, String>>>>, // set once an imported file is read
+    history: Vec<HistoryEntry>,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    history_sort: (HistoryColumn, bool), // column, ascending
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    history_selected: BTreeSet<usize>,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    history_message: Option<Result<String, String>>,
     preload_size: String,
+    #[cfg(not(target_arch = "wasm32"))]
     load_size: String,
     search_for: String,
+    search_range: (String, String), // positions where matches may start
+    max_digits: String,
+    time_limit: String,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    search_error: Option<String>,
+    curiosities: CuriosityOptions, // positions are counted with `position_convention`
+    structures: StructureOptions,
+    window_query: WindowQuery,
+    window_value: String, // digit sum or divisor
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    analysis_error: Option<String>,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    match_cursor: Option<MatchCursor>,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    download_rate: Option<f64>, // digits/s of the last preload, for ETAs
+    position_convention: PositionConvention,
+    lookup_position: String,
+    viewer: DigitViewer,
+    show_plots: bool,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    plots: PlotPanel,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    plots_message: Option<Result<String, String>>,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    lookup_result: Option<Result<String, String>>,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    settings: Settings,
+    #[cfg(feature = "persistence")]
+    config: SearchConfig, // the config of `search` when the state was saved
+    #[cfg_attr(feature = "persistence", serde(skip))]
     search: Search,
 }
 
 impl Default for TemplateApp {
     fn default() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let search = Search::with_config(SearchConfig::load_or_default()).unwrap_or_else(|err| {
+            eprintln!("Error while applying {}: {err}", SearchConfig::default_path().display());
+            Search::new()
+        });
+        #[cfg(target_arch = "wasm32")]
+        let search = Search::new();
+
         Self {
-            state: AppState::Input(InputInfo::new()),
+            jobs: JobManager::default(),
+            selected_job: None,
+            job_priority: 0,
+            #[cfg(not(target_arch = "wasm32"))]
+            caches: CacheList::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            file_dialog: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            saved_file: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            autosave_secs: 60,
+            #[cfg(not(target_arch = "wasm32"))]
+            last_autosave: Instant::now(),
+            #[cfg(not(target_arch = "wasm32"))]
+            new_cache_name: String::new(),
+            files_message: None,
+            #[cfg(target_arch = "wasm32")]
+            import_message: Rc::default(),
+            history: Vec::new(),
+            history_sort: (HistoryColumn::Order, false),
+            history_selected: BTreeSet::new(),
+            history_message: None,
             preload_size: Default::default(),
+            #[cfg(not(target_arch = "wasm32"))]
             load_size: Default::default(),
             search_for: Default::default(),
-            search: Search::new(),
+            search_range: Default::default(),
+            max_digits: Default::default(),
+            time_limit: Default::default(),
+            search_error: None,
+            curiosities: CuriosityOptions::default(),
+            structures: StructureOptions::default(),
+            window_query: WindowQuery::default(),
+            window_value: String::new(),
+            analysis_error: None,
+            match_cursor: None,
+            download_rate: None,
+            position_convention: Default::default(),
+            lookup_position: Default::default(),
+            viewer: DigitViewer::default(),
+            show_plots: false,
+            plots: PlotPanel::default(),
+            plots_message: None,
+            lookup_result: None,
+            settings: Settings::new(search.get_config()),
+            #[cfg(feature = "persistence")]
+            config: search.get_config().clone(),
+            search,
         }
     }
 }
 
 impl TemplateApp {
+    // Takes the persisted fields of a saved state, the running search and jobs stay
+    #[cfg(feature = "persistence")]
+    fn restore(&mut self, saved: TemplateApp) {
+        self.job_priority = saved.job_priority;
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.caches = saved.caches;
+            self.autosave_secs = saved.autosave_secs;
+            self.load_size = saved.load_size;
+        }
+        self.history = saved.history;
+        self.preload_size = saved.preload_size;
+        self.search_for = saved.search_for;
+        self.search_range = saved.search_range;
+        self.max_digits = saved.max_digits;
+        self.time_limit = saved.time_limit;
+        self.curiosities = saved.curiosities;
+        self.structures = saved.structures;
+        self.window_query = saved.window_query;
+        self.window_value = saved.window_value;
+        self.position_convention = saved.position_convention;
+        self.lookup_position = saved.lookup_position;
+        self.viewer = saved.viewer;
+        self.show_plots = saved.show_plots;
+
+        if let Err(err) = self.search.set_config(saved.config) {
+            self.settings.message = Some(Err(format!("Error while applying the saved settings: {err}")));
+            return;
+        }
+        self.settings = Settings::new(self.search.get_config());
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_caches(&mut self, ui: &mut Ui) {
+        // switching caches replaces the loaded digits, which jobs may be using
+        let idle = self.jobs.is_idle();
+        let current = self.caches.current_index();
+        let mut selected = current;
+        ui.add_enabled_ui(idle, |ui| {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_label("Cache")
+                    .selected_text(self.caches.current().name.as_str())
+                    .show_ui(ui, |ui| {
+                        for (i, cache) in self.caches.caches().iter().enumerate() {
+                            ui.selectable_value(&mut selected, i, cache.name.as_str())
+                              .on_hover_text(cache.path.display().to_string());
+                        }
+                    });
+                if ui.button("Open...").on_hover_text("Use another file for this cache").clicked() {
+                    self.file_dialog = Some(FileDialog::new(DialogMode::Open, &self.caches.current().path));
+                }
+                if ui.button("Save as...").on_hover_text("Write loaded digits to another file and use it for this cache, a .pidc file stores them in the compact binary format").clicked() {
+                    self.file_dialog = Some(FileDialog::new(DialogMode::Save, &self.caches.current().path));
+                }
+                if ui.button("Remove").on_hover_text("Forget this cache, the file is kept").clicked() {
+                    match self.caches.remove(current) {
+                        Ok(()) => self.switch_cache(),
+                        Err(err) => self.files_message = Some(Err(err)),
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.new_cache_name);
+                if ui.button("New cache").clicked() {
+                    match self.caches.add(self.new_cache_name.as_str()) {
+                        Ok(index) => {
+                            self.new_cache_name.clear();
+                            self.caches.select(index);
+                            self.switch_cache();
+                        },
+                        Err(err) => self.files_message = Some(Err(err)),
+                    }
+                }
+            });
+        });
+        ui.label(format!("File: {}", self.caches.current().path.display()));
+
+        if selected != current {
+            self.caches.select(selected);
+            self.switch_cache();
+        }
+    }
+
+    // Loads the current cache file and its journal in place of the previous one
+    #[cfg(not(target_arch = "wasm32"))]
+    fn switch_cache(&mut self) {
+        self.search.clear_digits();
+        self.search.set_journal(Some(self.journal_path()));
+        let path = self.caches.current().path.clone();
+        self.files_message = None;
+        if path.exists() {
+            if let Err(err) = self.load_digits() {
+                self.files_message = Some(Err(format!("Error while reading {}: {err}", path.display())));
+            }
+        }
+        if let Err(err) = self.search.resume_from_journal() {
+            self.files_message = Some(Err(format!("Error while reading {}: {err}", self.journal_path().display())));
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_file_dialog(&mut self, ctx: &egui::CtxRef) {
+        let dialog = match &mut self.file_dialog {
+            Some(dialog) => dialog,
+            None => return,
+        };
+        let mode = dialog.mode();
+        match dialog.show(ctx) {
+            Some(DialogResult::Picked(path)) => {
+                self.file_dialog = None;
+                self.caches.set_path(path);
+                match mode {
+                    DialogMode::Open => self.switch_cache(),
+                    DialogMode::Save => {
+                        self.search.set_journal(Some(self.journal_path()));
+                        self.files_message = Some(self.save_digits()
+                            .map(|_| format!("Saved to {}", self.caches.current().path.display()))
+                            .map_err(|err| err.to_string()));
+                    },
+                }
+            },
+            Some(DialogResult::Cancelled) => self.file_dialog = None,
+            None => {},
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
     fn show_files_control(&mut self, ui: &mut Ui) {
+        self.show_caches(ui);
+
+        let cache_file = self.caches.current().path.display().to_string();
         if ui.button("Read all")
-             .on_hover_text("Read all digits stored in pi.txt")
+             .on_hover_text(format!("Read all digits stored in {cache_file}"))
              .clicked()
         {
-            if self.load_digits().is_err() {
-                eprintln!("Error while loading digits");
-            }
+            self.files_message = Some(self.load_digits().map(|report| report.to_string()).map_err(|err| format!("Error while loading digits: {err}")));
         }
 
         ui.horizontal(|ui| {
             if ui.button("Read")
-                 .on_hover_text("Read digits stored in pi.txt")
+                 .on_hover_text(format!("Read digits stored in {cache_file}"))
                  .clicked()
             {
-                if self.load_size.len() > 0 && self.load_size.chars().all(char::is_numeric) {
-                    if self.load_n_digits(self.load_size.parse().unwrap()).is_err() {
-                        eprintln!("Error while loading digits");
-                    }
-                }
+                self.files_message = Some(match parse_digit_count(self.load_size.as_str()) {
+                    Ok(n) => self.load_n_digits(n).map(|report| report.to_string()).map_err(|err| format!("Error while loading digits: {err}")),
+                    Err(err) => Err(err),
+                });
             }
             ui.text_edit_singleline(&mut self.load_size);
         });
 
         if ui.button("Write loaded")
-             .on_hover_text("Write all loaded digits to pi.txt")
+             .on_hover_text(format!("Write all loaded digits to {cache_file}"))
              .clicked()
         {
-            if self.save_digits().is_err() {
-                eprintln!("Error while saving digits");
-            }
+            self.files_message = Some(self.save_digits()
+                .map(|_| format!("Saved to {cache_file}"))
+                .map_err(|err| format!("Error while saving digits: {err}")));
+        }
+
+        match &self.files_message {
+            Some(Ok(message)) => { ui.label(message); },
+            Some(Err(err)) => { ui.colored_label(egui::Color32::RED, err); },
+            None => {},
         }
-        
+
         ui.add_space(10f32);
     }
 
-    fn input_state(&mut self, ui: &mut Ui) {
-        self.show_files_control(ui);
+    fn show_settings(&mut self, ui: &mut Ui) {
+        egui::CollapsingHeader::new("Settings").show(ui, |ui| {
+            let settings = &mut self.settings;
+            egui::Grid::new("settings_grid").show(ui, |ui| {
+                ui.label("API URL: ");
+                ui.text_edit_singleline(&mut settings.base_url);
+                ui.end_row();
 
-        if let AppState::Input(info) = &mut self.state {
-            ui.label(format!("Digits loaded: {}", self.search.digits_loaded()));
+                ui.label("Verify TLS: ");
+                ui.checkbox(&mut settings.verify_tls, "");
+                ui.end_row();
 
-            let mut new_state = None;
-            egui::Grid::new("input_grid").max_col_width(120f32).show(ui, |ui| {
-                ui.label("Preload: ");
-                ui.add_enabled(true, egui::TextEdit::singleline(&mut self.preload_size));
-                if ui.button("Preload").clicked() {
-                    if self.preload_size.len() > 0 && self.preload_size.chars().all(char::is_numeric) {
-                        new_state = Some(AppState::Preload(PreloadInfo::new(&info, &mut self.search, self.preload_size.parse().unwrap())));
-                    }
+                ui.label("CA bundle: ");
+                ui.text_edit_singleline(&mut settings.ca_bundle).on_hover_text("PEM file with additional root certificates");
+                ui.end_row();
+
+                ui.label("Proxy: ");
+                ui.text_edit_singleline(&mut settings.proxy).on_hover_text("e.g. http://proxy.local:3128");
+                ui.end_row();
+
+                ui.label("Connect timeout (s): ");
+                ui.text_edit_singleline(&mut settings.connect_timeout).on_hover_text("0 or empty to disable");
+                ui.end_row();
+
+                ui.label("Read timeout (s): ");
+                ui.text_edit_singleline(&mut settings.read_timeout).on_hover_text("0 or empty to disable");
+                ui.end_row();
+
+                ui.label("User agent: ");
+                ui.text_edit_singleline(&mut settings.user_agent);
+                ui.end_row();
+
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    ui.label("Threads: ");
+                    ui.text_edit_singleline(&mut settings.workers).on_hover_text(format!("Workers of a preload or file search, 1 to {MAX_WORKERS}"));
+                    ui.end_row();
                 }
+
+                ui.label("Digits per request: ");
+                ui.text_edit_singleline(&mut settings.digits_per_request).on_hover_text(format!("1 to {MAX_DIGITS_PER_REQUEST}"));
                 ui.end_row();
 
-                ui.label("Search for: ");
-                ui.add_enabled(true, egui::TextEdit::singleline(&mut self.search_for));
-                if ui.button("Search").clicked() {
-                    if self.search_for.len() > 0 && self.search_for.chars().all(char::is_numeric) {
-                        new_state = Some(AppState::Search(SearchInfo::new(&info, &mut self.search, self.search_for.as_str())));
+                ui.label("Requests at a time: ");
+                ui.text_edit_singleline(&mut settings.max_in_flight).on_hover_text("Shared by all running jobs");
+                ui.end_row();
+
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    ui.label("Requests per second: ");
+                    ui.text_edit_singleline(&mut settings.requests_per_second).on_hover_text("0 or empty for no limit");
+                    ui.end_row();
+                }
+            });
+
+            if !settings.verify_tls {
+                ui.colored_label(egui::Color32::YELLOW, "Certificate validation is disabled");
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Apply").clicked() {
+                    let res = self.settings.to_config().and_then(|config| {
+                        self.search.set_config(config).map_err(|err| err.to_string())
+                    });
+                    self.settings.message = Some(res.map(|_| "Applied to new jobs".to_string()));
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    let path = SearchConfig::default_path();
+                    if ui.button("Save to file")
+                         .on_hover_text(format!("Write these settings to {}", path.display()))
+                         .clicked()
+                    {
+                        let res = self.settings.to_config().and_then(|config| {
+                            config.save(&path).map_err(|err| err.to_string())
+                        });
+                        self.settings.message = Some(res.map(|_| format!("Saved to {}", path.display())));
+                    }
+                    if ui.button("Load from file")
+                         .on_hover_text(format!("Read settings from {}", path.display()))
+                         .clicked()
+                    {
+                        match SearchConfig::load(&path) {
+                            Ok(config) => {
+                                self.settings = Settings::new(&config);
+                                self.settings.message = Some(Ok(format!("Loaded from {}, press Apply to use", path.display())));
+                            },
+                            Err(err) => self.settings.message = Some(Err(err.to_string())),
+                        }
                     }
                 }
-                ui.end_row();
             });
 
-            if new_state.is_some() {
-                self.state = new_state.unwrap();
+            match &self.settings.message {
+                Some(Ok(message)) => { ui.label(message); },
+                Some(Err(err)) => { ui.colored_label(egui::Color32::RED, err); },
+                None => {},
             }
-        }
+        });
     }
 
-    fn preload_state(&mut self, ui: &mut Ui) {
+    fn main_view(&mut self, ui: &mut Ui) {
         self.show_files_control(ui);
-        
-        if let AppState::Preload(info) = &mut self.state {
-            ui.label("Preloading...");
-            
-            loop {
-                let loaded_size_res = info.loaded_size_rec.try_recv();
-                match loaded_size_res {
-                    Ok(loaded_size) => info.loaded_size = loaded_size,
-                    Err(_) => { break; },
+        self.show_settings(ui);
+
+        let convention = &mut self.position_convention;
+        egui::ComboBox::from_label("Positions")
+            .selected_text(convention.name())
+            .show_ui(ui, |ui| {
+                for c in PositionConvention::ALL {
+                    ui.selectable_value(convention, c, c.name());
                 }
+            });
+        ui.checkbox(&mut self.show_plots, "Visualizations");
+
+        ui.label(format!("Digits loaded: {}", self.search.digits_loaded()));
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let pending = self.search.pending_digits();
+            if pending > 0 {
+                ui.label(format!("Digits from an interrupted preload: {pending} (preload to continue)"));
             }
+        }
 
-            ui.label(format!("Loaded {}/{} ({}%)", info.loaded_size, info.preload_size, (info.loaded_size as f32 / info.preload_size as f32 * 100f32) as u32));
-        
-            let result_res = info.result_rec.try_recv();
-            match result_res {
-                Ok(_) => {
-                    self.search.into_idle();
-                    self.state = AppState::Input(InputInfo::new());
-                },
-                Err(err) => {
-                    match err {
-                        TryRecvError::Empty => {},
-                        TryRecvError::Disconnected => { eprintln!("Preload thread is dead"); },
-                    }
+        let mut new_job = None;
+        let mut search = false;
+        let mut lookup = false;
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut search_file = false;
+        egui::Grid::new("input_grid").max_col_width(120f32).show(ui, |ui| {
+            ui.label("Preload: ");
+            ui.add_enabled(true, egui::TextEdit::singleline(&mut self.preload_size));
+            if ui.button("Preload").clicked() {
+                match parse_digit_count(self.preload_size.as_str()) {
+                    Ok(count) => {
+                        self.search_error = None;
+                        new_job = Some(JobKind::Preload { count });
+                    },
+                    Err(err) => self.search_error = Some(err),
+                }
+            }
+            ui.end_row();
+
+            ui.label("Search for: ");
+            ui.add_enabled(true, egui::TextEdit::singleline(&mut self.search_for));
+            search = ui.button("Search").clicked();
+            ui.end_row();
+
+            ui.label("From: ").on_hover_text("Positions where matches may start, empty for no limit");
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut self.search_range.0).desired_width(80f32).hint_text("start"));
+                ui.label("to");
+                ui.add(egui::TextEdit::singleline(&mut self.search_range.1).desired_width(80f32).hint_text("end"));
+            });
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                search_file = ui.button("Search file")
+                    .on_hover_text("Search the cache file on disk without loading it")
+                    .clicked();
+            }
+            ui.end_row();
+
+            ui.label("Max digits: ").on_hover_text("Give up after searching this many positions");
+            ui.add(egui::TextEdit::singleline(&mut self.max_digits).hint_text("no limit"));
+            ui.end_row();
+
+            ui.label("Time limit (s): ");
+            ui.add(egui::TextEdit::singleline(&mut self.time_limit).hint_text("no limit"));
+            ui.end_row();
+
+            ui.label("Priority: ").on_hover_text("Queued jobs with a higher priority start first");
+            ui.add(egui::DragValue::new(&mut self.job_priority).clamp_range(-10i32..=10i32));
+            ui.end_row();
+
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                ui.label("Autosave every (s): ").on_hover_text("Save the cache this often during preloads, 0 turns it off");
+                ui.add(egui::DragValue::new(&mut self.autosave_secs).clamp_range(0u64..=3600u64));
+                ui.end_row();
+            }
+
+            ui.label("Digits at: ");
+            ui.add_enabled(true, egui::TextEdit::singleline(&mut self.lookup_position));
+            lookup = ui.button("Show").clicked();
+            ui.end_row();
+        });
+
+        let valid_pattern = validate_pattern(self.search_for.as_str());
+        if search {
+            self.search_error = None;
+            match valid_pattern.clone().and_then(|_| self.search_bounds()) {
+                Ok(bounds) => new_job = Some(JobKind::Search { pattern: self.search_for.clone(), bounds }),
+                Err(err) => self.search_error = Some(err),
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if search_file {
+            self.search_error = None;
+            match valid_pattern.clone().and_then(|_| parse_range(&self.search_range, self.position_convention, self.search.integer_digits())) {
+                Ok(range) => new_job = Some(JobKind::FileSearch { path: self.caches.current().path.clone(), pattern: self.search_for.clone(), range }),
+                Err(err) => self.search_error = Some(err),
+            }
+        }
+        if let Some(err) = &self.search_error {
+            ui.colored_label(egui::Color32::RED, err);
+        }
+        else if valid_pattern.is_ok() {
+            if let Ok(bounds) = self.search_bounds() {
+                self.show_estimate(ui, &bounds);
+            }
+        }
+        if let Some(kind) = new_job {
+            self.selected_job = Some(self.jobs.submit(kind, self.job_priority));
+        }
+        self.show_match_cursor(ui);
+        self.show_analyses(ui);
+
+        if lookup {
+            self.lookup_result = Some(self.lookup_digits(self.lookup_position.as_str(), 20usize));
+            if let Ok(offset) = self.position_convention.parse(self.lookup_position.as_str(), self.search.integer_digits()) {
+                self.viewer.go_to(offset);
+            }
+        }
+
+        match &self.lookup_result {
+            Some(Ok(digits)) => { ui.monospace(digits); },
+            Some(Err(err)) => { ui.colored_label(egui::Color32::RED, err); },
+            None => {},
+        }
+
+        self.show_viewer(ui);
+    }
+
+    fn search_bounds(&self) -> Result<SearchBounds, String> {
+        let range = parse_range(&self.search_range, self.position_convention, self.search.integer_digits())?;
+        let mut bounds = SearchBounds::from(range.as_ref().map_or(0usize, |range| range.start));
+        bounds.end = range.map(|range| range.end).filter(|&end| end != usize::MAX);
+        if !self.max_digits.trim().is_empty() {
+            bounds.max_digits = Some(self.max_digits.trim().parse().map_err(|_| format!("Max digits must be a whole number, got \"{}\"", self.max_digits))?);
+        }
+        if !self.time_limit.trim().is_empty() {
+            let secs: f64 = self.time_limit.trim().parse().map_err(|_| format!("Time limit must be a number of seconds, got \"{}\"", self.time_limit))?;
+            if secs.is_nan() || secs <= 0f64 {
+                return Err("Time limit must be positive".to_string());
+            }
+            bounds.time_limit = Some(std::time::Duration::try_from_secs_f64(secs).map_err(|_| "Time limit is too long, leave it empty for no limit".to_string())?);
+        }
+        Ok(bounds)
+    }
+
+    fn show_estimate(&self, ui: &mut Ui, bounds: &SearchBounds) {
+        let estimate = self.search.estimate(self.search_for.as_str(), bounds, self.download_rate);
+        let integer_digits = self.search.integer_digits();
+        let mut text = format!(
+            "Expected around {}, {} chance in the cached digits",
+            format_expected(estimate.expected_position, self.position_convention, integer_digits),
+            format_probability(estimate.in_cache),
+        );
+        if let Some(in_bounds) = estimate.in_bounds {
+            text.push_str(format!(", {} within the limits", format_probability(in_bounds)).as_str());
+        }
+        match estimate.eta {
+            Some(eta) if eta.is_zero() => {},
+            Some(eta) => text.push_str(format!(", about {} of downloading", format_duration(eta)).as_str()),
+            None if self.download_rate.is_some() => text.push_str(", about — of downloading"),
+            None if estimate.in_cache < 0.5 => text.push_str(", preload to estimate the download time"),
+            None => {},
+        }
+        ui.label(text).on_hover_text("Assuming the digits of pi behave like random digits");
+    }
+
+    fn show_match_cursor(&mut self, ui: &mut Ui) {
+        let cursor = match &mut self.match_cursor {
+            Some(cursor) => cursor,
+            None => return,
+        };
+        let mut previous = false;
+        let mut next = false;
+        ui.horizontal(|ui| {
+            ui.label(format!("\"{}\" at {}", cursor.pattern, self.position_convention.format(cursor.index, self.search.integer_digits())));
+            previous = ui.button("Find previous").on_hover_text("Previous match within the cached digits").clicked();
+            let searching = cursor.next_job.is_some();
+            next = ui.add_enabled(!searching, egui::Button::new(if searching { "Searching..." } else { "Find next" })).clicked();
+        });
+        if let Some(message) = &cursor.message {
+            ui.label(message);
+        }
+
+        if previous {
+            let started = instant::Instant::now();
+            match self.search.find_previous(cursor.pattern.as_str(), cursor.index) {
+                Some(index) => {
+                    self.history.push(HistoryEntry {
+                        pattern: cursor.pattern.clone(),
+                        kind: JobKind::FindPrevious { pattern: cursor.pattern.clone(), before: cursor.index },
+                        index: Some(index),
+                        processed: cursor.index - index,
+                        duration: started.elapsed().as_secs_f64(),
+                        source: ResultSource::Cache,
+                    });
+                    self.viewer.show_match(index, cursor.pattern.as_str());
+                    cursor.index = index;
+                    cursor.message = None;
                 },
+                None => cursor.message = Some("No earlier match in the cached digits".to_string()),
             }
         }
+        if next {
+            let kind = JobKind::Search { pattern: cursor.pattern.clone(), bounds: cursor.bounds.after(cursor.index) };
+            let id = self.jobs.submit(kind, self.job_priority);
+            cursor.next_job = Some(id);
+            cursor.message = None;
+            self.selected_job = Some(id);
+        }
     }
 
-    fn search_state(&mut self, ui: &mut Ui) {
-        self.show_files_control(ui);
-        
-        if let AppState::Search(info) = &mut self.state {
+    fn show_analyses(&mut self, ui: &mut Ui) {
+        let mut analysis = None;
+        egui::CollapsingHeader::new("Curiosities").show(ui, |ui| {
+            let options = &mut self.curiosities;
+            ui.checkbox(&mut options.self_locating, "Self-locating strings").on_hover_text("The digits of n starting at position n");
+            optional_run(ui, "Digits equal to their position mod 10, runs of at least", &mut options.digit_is_index_run, 3usize);
+            optional_run(ui, "Repeated digits, runs of at least", &mut options.repeated_run, 6usize);
+            if ui.button("Scan").on_hover_text("Scans the range above, the cached digits without one").clicked() {
+                options.convention = self.position_convention;
+                analysis = Some(Analysis::Curiosities(options.clone()));
+            }
+        });
+        egui::CollapsingHeader::new("Structures").show(ui, |ui| {
+            let options = &mut self.structures;
+            ui.label("The first occurrence of every length of");
+            ui.checkbox(&mut options.runs, "Runs of one digit");
+            ui.checkbox(&mut options.ascending, "Ascending sequences (123...)");
+            ui.checkbox(&mut options.descending, "Descending sequences (987...)");
+            ui.checkbox(&mut options.progressions, "Other arithmetic progressions (2468...)");
+            ui.checkbox(&mut options.palindromes, "Palindromes");
+            if ui.button("Scan").on_hover_text("Scans the range above, the cached digits without one").clicked() {
+                analysis = Some(Analysis::Structures(options.clone()));
+            }
+        });
+        let mut window_query = None;
+        egui::CollapsingHeader::new("Window queries").show(ui, |ui| {
+            let query = &mut self.window_query;
             ui.horizontal(|ui| {
-                ui.label("Search for: ");
-                ui.add_enabled(false, egui::TextEdit::singleline(&mut self.search_for));
+                ui.label("First window of");
+                ui.add(egui::DragValue::new(&mut query.digits).clamp_range(1usize..=1000usize));
+                ui.label("digits:");
+                egui::ComboBox::from_id_source("window_condition")
+                    .selected_text(query.condition.name())
+                    .show_ui(ui, |ui| {
+                        for condition in [WindowCondition::DigitSum(0), WindowCondition::Prime, WindowCondition::DivisibleBy(1)] {
+                            if ui.selectable_label(std::mem::discriminant(&query.condition) == std::mem::discriminant(&condition), condition.name()).clicked() {
+                                query.condition = condition;
+                            }
+                        }
+                    });
+                if query.condition != WindowCondition::Prime {
+                    ui.add(egui::TextEdit::singleline(&mut self.window_value).desired_width(80f32));
+                }
             });
+            if ui.button("Find").on_hover_text("Searches from the start of the range above, fetching digits past the cache").clicked() {
+                window_query = Some(parse_window_query(*query, self.window_value.as_str()));
+            }
+        });
 
-            loop {
-                let processed_res = info.processed_size_rec.try_recv();
-                match processed_res {
-                    Ok(pro) => info.processed_size = pro,
-                    Err(_) => { break; },
+        if let Some(analysis) = analysis {
+            self.analysis_error = None;
+            match self.analysis_bounds() {
+                Ok(bounds) => self.selected_job = Some(self.jobs.submit(JobKind::Analysis { analysis, bounds }, self.job_priority)),
+                Err(err) => self.analysis_error = Some(err),
+            }
+        }
+        // a query stops at its first match, it doesn't need an end
+        if let Some(query) = window_query {
+            self.analysis_error = None;
+            match query.and_then(|query| Ok((query, self.search_bounds()?))) {
+                Ok((query, bounds)) => self.selected_job = Some(self.jobs.submit(JobKind::Analysis { analysis: Analysis::Window(query), bounds }, self.job_priority)),
+                Err(err) => self.analysis_error = Some(err),
+            }
+        }
+        if let Some(err) = &self.analysis_error {
+            ui.colored_label(egui::Color32::RED, err);
+        }
+    }
+
+    // An analysis without an end would run forever, it stops at the end of the cache instead
+    fn analysis_bounds(&self) -> Result<SearchBounds, String> {
+        let mut bounds = self.search_bounds()?;
+        if bounds.limit().is_none() {
+            let loaded = self.search.digits_loaded();
+            if bounds.start >= loaded {
+                return Err("Nothing to scan, preload digits or give an end".to_string());
+            }
+            bounds.end = Some(loaded);
+        }
+        Ok(bounds)
+    }
+
+    // Starts queued jobs and records the results of the finished ones
+    fn update_jobs(&mut self) {
+        let done = self.jobs.update(&mut self.search);
+        let preload_rate = self.jobs.jobs().iter()
+            .filter(|job| matches!(job.kind, JobKind::Preload { .. }) && (job.state == JobState::Running || done.contains(&job.id)))
+            .filter_map(|job| job.status.progress.as_ref())
+            .filter(|progress| progress.bytes > 0 && progress.rate > 0f64)
+            .map(|progress| progress.rate)
+            .next_back();
+        if preload_rate.is_some() {
+            self.download_rate = preload_rate;
+        }
+        for id in done {
+            let job = self.jobs.get(id).unwrap();
+            if let Some(entry) = job.history_entry() {
+                if let Some(index) = entry.index {
+                    self.viewer.show_match(index, entry.pattern.as_str());
+                }
+                self.history.push(entry);
+            }
+            let stepping = self.match_cursor.as_ref().is_some_and(|cursor| cursor.next_job == Some(id));
+            if let JobKind::Search { pattern, bounds } = &job.kind {
+                match job.status.matched {
+                    Some(index) if job.state == JobState::Finished => {
+                        self.match_cursor = Some(MatchCursor { pattern: pattern.clone(), index, bounds: bounds.clone(), next_job: None, message: None });
+                    },
+                    _ if stepping => {
+                        let cursor = self.match_cursor.as_mut().unwrap();
+                        cursor.next_job = None;
+                        cursor.message = Some(match (&job.status.not_found, &job.status.error) {
+                            (Some((searched, reason)), _) => format!("No later match in the next {} positions ({})", searched.len(), reason.name()),
+                            (_, Some(err)) => err.clone(),
+                            _ => "No later match".to_string(),
+                        });
+                    },
+                    _ => {},
                 }
             }
-            
-            ui.label(format!("Processed: {}", info.processed_size));
-
-            let result_res = info.result_rec.try_recv();
-            match result_res {
-                Ok(index) => {
-                    loop {
-                        let processed_res = info.processed_size_rec.try_recv();
-                        match processed_res {
-                            Ok(pro) => info.processed_size = pro,
-                            Err(_) => { break; },
+            #[cfg(target_arch = "wasm32")]
+            if let Err(err) = self.save_digits() {
+                self.files_message = Some(Err(err));
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.jobs.running().any(|job| matches!(job.kind, JobKind::Preload { .. })) {
+            self.autosave();
+        }
+        else {
+            self.last_autosave = Instant::now();
+        }
+    }
+
+    fn show_jobs(&mut self, ctx: &egui::CtxRef) {
+        if self.jobs.jobs().is_empty() {
+            return;
+        }
+        let integer_digits = self.search.integer_digits();
+        let convention = self.position_convention;
+
+        egui::SidePanel::left("jobs_panel").show(ctx, |ui| {
+            ui.heading("Jobs");
+            ui.horizontal(|ui| {
+                ui.label("Run at once: ");
+                let mut max_running = self.jobs.max_running();
+                if ui.add(egui::DragValue::new(&mut max_running).clamp_range(1usize..=16usize)).changed() {
+                    self.jobs.set_max_running(max_running);
+                }
+                if ui.button("Clear finished").clicked() {
+                    self.jobs.remove_done();
+                }
+            });
+
+            let mut action = None;
+            egui::ScrollArea::vertical().id_source("jobs_scroll").max_height(300f32).show(ui, |ui| {
+                egui::Grid::new("jobs_grid").striped(true).show(ui, |ui| {
+                    for job in self.jobs.jobs() {
+                        if ui.selectable_label(self.selected_job == Some(job.id), format!("#{}", job.id)).clicked() {
+                            self.selected_job = Some(job.id);
+                        }
+                        ui.label(job.kind.name());
+                        ui.monospace(job.kind.describe());
+                        ui.label(job.state.name());
+                        match job.status.progress.as_ref().and_then(|p| p.fraction()) {
+                            Some(fraction) => ui.label(format!("{}%", (fraction * 100f32) as u32)),
+                            None => ui.label(job.status.done().to_string()),
+                        };
+
+                        let queued = job.state == JobState::Queued;
+                        let mut priority = job.priority;
+                        if ui.add_enabled(queued, egui::DragValue::new(&mut priority).clamp_range(-10i32..=10i32)).on_hover_text("Priority").changed() {
+                            action = Some(JobAction::Priority(job.id, priority));
+                        }
+                        if ui.add_enabled(queued, egui::Button::new("^").small()).on_hover_text("Earlier in the queue").clicked() {
+                            action = Some(JobAction::Move(job.id, -1));
+                        }
+                        if ui.add_enabled(queued, egui::Button::new("v").small()).on_hover_text("Later in the queue").clicked() {
+                            action = Some(JobAction::Move(job.id, 1));
                         }
+                        if ui.add_enabled(!job.state.is_done(), egui::Button::new("Cancel").small()).clicked() {
+                            action = Some(JobAction::Cancel(job.id));
+                        }
+                        ui.end_row();
                     }
+                });
+            });
 
-                    self.search.into_idle();
-                    self.state = AppState::Found(FoundInfo::new(&info, index));
-                },
-                Err(err) => {
-                    match err {
-                        TryRecvError::Empty => {},
-                        TryRecvError::Disconnected => { panic!("Search thread is dead"); },
+            match action {
+                Some(JobAction::Priority(id, priority)) => self.jobs.set_priority(id, priority),
+                Some(JobAction::Move(id, direction)) => self.jobs.move_job(id, direction),
+                Some(JobAction::Cancel(id)) => {
+                    self.jobs.cancel(id);
+                    if let Some(cursor) = self.match_cursor.as_mut().filter(|cursor| cursor.next_job == Some(id)) {
+                        cursor.next_job = None;
                     }
                 },
+                None => {},
             }
+
+            // details of the selected job
+            if let Some(job) = self.selected_job.and_then(|id| self.jobs.get(id)) {
+                ui.separator();
+                ui.label(format!("#{} {}: {}", job.id, job.kind.name(), job.kind.describe()));
+                ui.label(format!("State: {}, {}", job.state.name(), format_duration(job.elapsed())));
+                if job.state != JobState::Queued {
+                    show_job_status(ui, &job.status, |offset| convention.format(offset, integer_digits));
+                }
+                if let (Some(index), Some(pattern)) = (job.status.matched, job.kind.pattern()) {
+                    if ui.button("Show").clicked() {
+                        self.viewer.show_match(index, pattern);
+                    }
+                }
+                else if let Some((searched, reason)) = &job.status.not_found {
+                    ui.label(format!("Not found in the {} positions from {} ({})", searched.len(), convention.format(searched.start, integer_digits), reason.name()));
+                }
+                show_job_estimate(ui, job, |offset| format_expected(offset, convention, integer_digits));
+                if let Some(index) = show_findings(ui, job, convention, integer_digits) {
+                    let finding = &job.status.findings[index];
+                    self.viewer.show_match(finding.position, finding.digits.as_str());
+                }
+            }
+        });
+    }
+
+    fn show_viewer(&mut self, ui: &mut Ui) {
+        let digits = self.search.get_digits();
+        let digits = digits.lock().unwrap();
+        if digits.is_empty() {
+            return;
         }
+        ui.separator();
+        self.viewer.show(ui, digits.as_str(), self.position_convention, self.search.integer_digits());
     }
 
-    fn found_state(&mut self, ui: &mut Ui) {
-        if let AppState::Found(info) = &mut self.state {
+    fn show_history(&mut self, ctx: &egui::CtxRef) {
+        if self.history.is_empty() {
+            return;
+        }
+        let integer_digits = self.search.integer_digits();
+        let convention = self.position_convention;
+
+        let mut action = None;
+        egui::SidePanel::right("history_panel").show(ctx, |ui| {
+            ui.heading("History");
+
             ui.horizontal(|ui| {
-                ui.label("Search for: ");
-                ui.add_enabled(false, egui::TextEdit::singleline(&mut self.search_for));
+                if ui.button("Export CSV").clicked() {
+                    let csv = to_csv(&self.history, convention, integer_digits);
+                    self.history_message = Some(export_text("history.csv", csv.as_str()));
+                }
+                if ui.button("Export JSON").clicked() {
+                    let json = to_json(&self.history, convention, integer_digits);
+                    self.history_message = Some(export_text("history.json", json.as_str()));
+                }
+                if ui.button("Clear selection").clicked() {
+                    self.history_selected.clear();
+                }
             });
+            match &self.history_message {
+                Some(Ok(message)) => { ui.label(message); },
+                Some(Err(err)) => { ui.colored_label(egui::Color32::RED, err); },
+                None => {},
+            }
 
-            ui.label(format!("Processed: {}", info.processed));
-            ui.label(format!("Index: {:?}", info.index));
+            let (sort_column, ascending) = self.history_sort;
+            let order = sorted_indices(&self.history, sort_column, ascending);
 
-            if ui.button("Back").clicked() {
-                self.search_for.clear();
-                self.state = AppState::Input(InputInfo::new());
+            egui::ScrollArea::vertical().max_height(400f32).show(ui, |ui| {
+                egui::Grid::new("history_grid").striped(true).show(ui, |ui| {
+                    ui.label("").on_hover_text("Select entries to compare");
+                    for column in HistoryColumn::ALL {
+                        let arrow = match (column == sort_column, ascending) {
+                            (true, true) => " ^",
+                            (true, false) => " v",
+                            (false, _) => "",
+                        };
+                        if ui.small_button(format!("{}{arrow}", column.name())).clicked() {
+                            self.history_sort = (column, column != sort_column || !ascending);
+                        }
+                    }
+                    ui.end_row();
+
+                    for i in order {
+                        let entry = &self.history[i];
+
+                        let mut selected = self.history_selected.contains(&i);
+                        if ui.checkbox(&mut selected, "").changed() {
+                            if selected {
+                                self.history_selected.insert(i);
+                            }
+                            else {
+                                self.history_selected.remove(&i);
+                            }
+                        }
+                        ui.label((i + 1).to_string());
+                        ui.monospace(entry.pattern.as_str());
+                        ui.label(entry.mode());
+                        match entry.index {
+                            Some(index) => ui.label(convention.format(index, integer_digits)),
+                            None => ui.label("not found"),
+                        };
+                        ui.label(entry.processed.to_string());
+                        ui.label(std::time::Duration::try_from_secs_f64(entry.duration).map_or("—".to_string(), format_duration));
+                        ui.label(entry.source.name());
+                        if ui.small_button("Re-run").clicked() {
+                            action = Some(HistoryAction::Rerun(i));
+                        }
+                        if entry.index.is_some() && ui.small_button("Show").clicked() {
+                            action = Some(HistoryAction::Show(i));
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+
+            // positions of the selected entries relative to the first one
+            if self.history_selected.len() >= 2 {
+                ui.separator();
+                ui.label("Compare");
+                let mut selected = self.history_selected.iter().map(|&i| &self.history[i]);
+                let first = selected.next().unwrap();
+                for entry in selected {
+                    let text = match (first.index, entry.index) {
+                        (Some(a), Some(b)) => format!("{} is {} digits {} {}", entry.pattern, a.abs_diff(b), if b >= a { "after" } else { "before" }, first.pattern),
+                        _ => format!("{} / {}: both need a result", first.pattern, entry.pattern),
+                    };
+                    ui.label(text);
+                }
             }
+        });
+
+        match action {
+            Some(HistoryAction::Rerun(i)) => {
+                self.search_for = self.history[i].pattern.clone();
+                self.selected_job = Some(self.jobs.submit(self.history[i].kind.clone(), self.job_priority));
+            },
+            Some(HistoryAction::Show(i)) => {
+                if let Some(index) = self.history[i].index {
+                    self.viewer.show_match(index, self.history[i].pattern.as_str());
+                }
+            },
+            None => {},
         }
     }
 
-    fn load_digits(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let mut pi_file = File::open("pi.txt")?;
+    fn show_plots(&mut self, ctx: &egui::CtxRef) {
         let digits = self.search.get_digits();
+        let plots = &mut self.plots;
+        let message = &mut self.plots_message;
+        egui::Window::new("Visualizations").open(&mut self.show_plots).default_width(460f32).show(ctx, |ui| {
+            let export = plots.show(ui, digits.lock().unwrap().as_str());
+            if let Some(export) = export {
+                *message = Some(export_file(export.file_name.as_str(), &export.bytes, export.mime_type));
+            }
+            match message {
+                Some(Ok(message)) => { ui.label(message.as_str()); },
+                Some(Err(err)) => { ui.colored_label(egui::Color32::RED, err.as_str()); },
+                None => {},
+            }
+        });
+    }
 
-        let file_size = pi_file.metadata()?.len() as usize;
-        let digits_size = digits.lock().unwrap().len();
-
-        if file_size > digits_size {
-            let mut file_digits = String::default();
-            pi_file.read_to_string(&mut file_digits)?;
+    #[cfg(not(target_arch = "wasm32"))]
+    fn journal_path(&self) -> std::path::PathBuf {
+        let mut path = self.caches.current().path.clone().into_os_string();
+        path.push(".journal");
+        path.into()
+    }
 
-            digits.lock().unwrap().clear();
-            digits.lock().unwrap().push_str(file_digits.as_str());
+    // `count` digits from the cache starting at a position in the current convention
+    fn lookup_digits(&self, position: &str, count: usize) -> Result<String, String> {
+        let offset = self.position_convention.parse(position, self.search.integer_digits())?;
+        let digits = self.search.get_digits();
+        let digits = digits.lock().unwrap();
+        if offset >= digits.len() {
+            return Err(format!("Only {} digits are loaded", digits.len()));
         }
+        Ok(digits[offset..(offset + count).min(digits.len())].to_string())
+    }
 
-        Ok(())
+    // Replaces the cache with the digits of a digit file if it has more of them
+    fn set_cache_data(&mut self, bytes: &[u8], complete: bool, max_digits: Option<usize>) -> Result<ImportReport, Box<dyn std::error::Error>> {
+        let (file_digits, report) = import_digits(bytes, complete)?;
+        self.set_cache_digits(file_digits, report, max_digits)
     }
 
-    fn load_n_digits(&mut self, count: usize) -> Result<(), Box<dyn std::error::Error>> {
-        let mut pi_file = File::open("pi.txt")?;
+    fn set_cache_digits(&mut self, mut file_digits: String, report: ImportReport, max_digits: Option<usize>) -> Result<ImportReport, Box<dyn std::error::Error>> {
+        if let Some(integer_digits) = report.integer_digits {
+            if integer_digits != self.search.integer_digits() {
+                return Err(format!("Cache has {integer_digits} digits before the decimal point, expected {}", self.search.integer_digits()).into());
+            }
+        }
+        if let Some(max_digits) = max_digits {
+            file_digits.truncate(max_digits);
+        }
+
         let digits = self.search.get_digits();
+        let mut digits = digits.lock().unwrap();
+        if file_digits.len() > digits.len() {
+            *digits = file_digits;
+        }
+        Ok(report)
+    }
 
-        let load_size = count.min(pi_file.metadata()?.len() as usize);
-        let digits_size = digits.lock().unwrap().len();
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_digits(&mut self) -> Result<ImportReport, Box<dyn std::error::Error>> {
+        let path = self.caches.current().path.clone();
+        let bytes = std::fs::read(&path)?;
+        let report = self.set_cache_data(&bytes, true, None)?;
 
-        if load_size > digits_size {
-            let mut buf = vec![0u8; load_size];
-            pi_file.read_exact(&mut buf)?;
-            let file_digits = String::from_utf8(buf)?;
+        // a file in the format we write can be appended to when saving, binary ones check that themselves
+        let plain = report.format == DigitFormat::Text && report.header_lines == 0 && report.position_labels == 0 && report.skipped.is_empty()
+            && bytes.len() == report.digits + usize::from(report.integer_digits.is_some());
+        if (plain || report.format == DigitFormat::Binary) && self.search.digits_loaded() == report.digits {
+            self.saved_file = Some(SavedFile { path, digits: report.digits, len: bytes.len() as u64 });
+        }
+        Ok(report)
+    }
 
-            digits.lock().unwrap().clear();
-            digits.lock().unwrap().push_str(file_digits.as_str());
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_n_digits(&mut self, count: usize) -> Result<ImportReport, Box<dyn std::error::Error>> {
+        let path = self.caches.current().path.clone();
+        let mut pi_file = File::open(&path)?;
+
+        // binary caches are read block by block
+        let mut magic = [0u8; 8];
+        let is_binary = pi_file.read_exact(&mut magic).is_ok() && is_binary_cache(&magic);
+        pi_file.rewind()?;
+        if is_binary {
+            let mut reader = CacheReader::open(&path)?;
+            let file_digits = reader.read_range(0..count)?;
+            let mut report = ImportReport::new(DigitFormat::Binary);
+            report.digits = file_digits.len();
+            report.integer_digits = Some(reader.header().integer_digits);
+            return self.set_cache_digits(file_digits, report, None);
         }
 
-        Ok(())
+        // formatting takes up room too, read more until there are enough digits
+        let mut bytes = Vec::new();
+        let mut chunk = count as u64 + 4096;
+        let complete = loop {
+            let read = (&mut pi_file).take(chunk).read_to_end(&mut bytes)?;
+            if (read as u64) < chunk {
+                break true;
+            }
+            if import_digits(&bytes, false)?.0.len() >= count {
+                break false;
+            }
+            chunk = bytes.len() as u64;
+        };
+        self.set_cache_data(&bytes, complete, Some(count))
     }
 
-    fn save_digits(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let mut pi_file = File::create("pi.txt")?;
+    #[cfg(not(target_arch = "wasm32"))]
+    // Autosaves of binary files stop at the last whole block, so the next one can append to it.
+    // The digits after it are still in the journal.
+    fn write_digits(&mut self, autosave: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let cache = self.caches.current().clone();
+        create_parent_dir(&cache.path)?;
+        let integer_digits = self.search.integer_digits();
+        // the digits are only locked to copy them, searches can go on while they're written
         let digits = self.search.get_digits();
+        let copy = |range: std::ops::Range<usize>| digits.lock().unwrap()[range].to_string();
+        let loaded = digits.lock().unwrap().len();
 
-        let file_size = pi_file.metadata()?.len() as usize;
-        let digits_size = digits.lock().unwrap().len();
+        // only the new digits are written if the file is still the one we saved or read
+        let saved = self.saved_file.clone().filter(|saved| {
+            saved.path == cache.path && saved.digits > integer_digits && saved.digits <= loaded
+        });
 
-        if file_size < digits_size {
-            pi_file.write_all(&digits.lock().unwrap().as_bytes()[file_size..])?;
+        // the file extension picks the format
+        let (end, len) = if cache.path.extension().is_some_and(|ext| ext == FILE_EXTENSION) {
+            let whole = loaded / BLOCK_DIGITS * BLOCK_DIGITS;
+            let end = match &saved {
+                Some(saved) if autosave && whole >= saved.digits => whole,
+                _ => loaded,
+            };
+            let appended = match &saved {
+                Some(saved) => append_cache(saved, copy(saved.digits..end).as_str())?,
+                None => None,
+            };
+            let len = match appended {
+                Some(len) => len,
+                None => {
+                    let metadata = CacheMetadata {
+                        integer_digits,
+                        constant: cache.name.as_str(),
+                        source: self.search.get_config().base_url.as_str(),
+                        created: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+                    };
+                    write_cache(&cache.path, copy(0..end).as_str(), &metadata)?
+                },
+            };
+            (end, len)
         }
+        else {
+            let len = match &saved {
+                Some(saved) if append_to(saved, copy(saved.digits..loaded).as_bytes())? => saved.len + (loaded - saved.digits) as u64,
+                _ => {
+                    let text = format_cache(copy(0..loaded).as_str(), integer_digits);
+                    write_atomic(&cache.path, text.as_bytes())?;
+                    text.len() as u64
+                },
+            };
+            (loaded, len)
+        };
+        self.saved_file = Some(SavedFile { path: cache.path, digits: end, len });
+        Ok(())
+    }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_digits(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.write_digits(false)?;
+        self.search.clear_journal()?;
         Ok(())
     }
+
+    // Saves while a preload is running, the journal is left to the preload
+    #[cfg(not(target_arch = "wasm32"))]
+    fn autosave(&mut self) {
+        if self.autosave_secs == 0 || self.last_autosave.elapsed().as_secs() < self.autosave_secs {
+            return;
+        }
+        self.last_autosave = Instant::now();
+        if let Err(err) = self.write_digits(true) {
+            self.files_message = Some(Err(format!("Autosave failed: {err}")));
+        }
+    }
+}
+
+// In the browser the cache lives in local storage and files go through the browser's upload/download
+#[cfg(target_arch = "wasm32")]
+impl TemplateApp {
+    fn show_files_control(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("Import file")
+                 .on_hover_text("Replace loaded digits with a file from your computer")
+                 .clicked()
+            {
+                if let Err(err) = import_cache(self.search.get_digits(), self.import_message.clone()) {
+                    self.files_message = Some(Err(format!("Error while opening the file picker: {err:?}")));
+                }
+            }
+
+            if ui.button("Export file")
+                 .on_hover_text("Download all loaded digits as pi.txt")
+                 .clicked()
+            {
+                let text = format_cache(self.search.get_digits().lock().unwrap().as_str(), self.search.integer_digits());
+                self.files_message = Some(download_file("pi.txt", text.as_str()).map(|_| "Downloaded pi.txt".to_string()).map_err(|err| format!("Error while exporting: {err:?}")));
+            }
+
+            if ui.button("Save in browser")
+                 .on_hover_text("Store all loaded digits in the browser storage")
+                 .clicked()
+            {
+                self.files_message = Some(self.save_digits().map(|_| "Saved in the browser storage".to_string()));
+            }
+        });
+
+        if let Some(message) = self.import_message.borrow_mut().take() {
+            self.files_message = Some(message);
+        }
+
+        match &self.files_message {
+            Some(Ok(message)) => { ui.label(message); },
+            Some(Err(err)) => { ui.colored_label(egui::Color32::RED, err); },
+            None => {},
+        }
+
+        ui.add_space(10f32);
+    }
+
+    fn load_digits(&mut self) {
+        match load_cache() {
+            Ok(Some(cached)) => {
+                if let Err(err) = self.set_cache_data(cached.as_bytes(), true, None) {
+                    self.files_message = Some(Err(format!("Error while reading the saved cache: {err}")));
+                }
+            },
+            Ok(None) => {},
+            Err(err) => self.files_message = Some(Err(format!("Error while reading the saved cache: {err:?}"))),
+        }
+    }
+
+    fn save_digits(&mut self) -> Result<(), String> {
+        let text = format_cache(self.search.get_digits().lock().unwrap().as_str(), self.search.integer_digits());
+        save_cache(text.as_str())
+    }
+}
+
+// Only ASCII digits, `char::is_numeric` lets "٣" through, and no more than a usize holds
+fn parse_digit_count(text: &str) -> Result<usize, String> {
+    let text = text.trim();
+    if text.is_empty() || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("\"{text}\" is not a number of digits"));
+    }
+    text.parse().map_err(|_| format!("{text} digits are more than can be loaded"))
+}
+
+// Only ASCII digits, like `parse_digit_count`
+fn validate_pattern(pattern: &str) -> Result<(), String> {
+    if pattern.is_empty() {
+        return Err("Enter the digits to search for".to_string());
+    }
+    if !pattern.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("\"{pattern}\" is not a sequence of digits"));
+    }
+    Ok(())
+}
+
+// Offsets where matches may start, from the positions in the range fields
+fn parse_range(fields: &(String, String), convention: PositionConvention, integer_digits: usize) -> Result<Option<std::ops::Range<usize>>, String> {
+    let (from, to) = (fields.0.trim(), fields.1.trim());
+    if from.is_empty() && to.is_empty() {
+        return Ok(None);
+    }
+    let start = if from.is_empty() { 0usize } else { convention.parse(from, integer_digits)? };
+    let end = if to.is_empty() { usize::MAX } else { convention.parse(to, integer_digits)? + 1 };
+    if start >= end {
+        return Err("The range is empty".to_string());
+    }
+    Ok(Some(start..end))
+}
+
+fn export_text(file_name: &str, text: &str) -> Result<String, String> {
+    export_file(file_name, text.as_bytes(), "text/plain")
+}
+
+// Writes an export next to the app, or downloads it in the browser
+fn export_file(file_name: &str, bytes: &[u8], _mime_type: &str) -> Result<String, String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    return std::fs::write(file_name, bytes).map(|_| format!("Saved to {file_name}")).map_err(|err| err.to_string());
+    #[cfg(target_arch = "wasm32")]
+    return download_bytes(file_name, bytes, _mime_type).map(|_| format!("Downloaded {file_name}")).map_err(|err| format!("{err:?}"));
+}
+
+// Renders any job uniformly: progress bar (if the total is known), throughput, ETA and warnings
+fn show_job_status(ui: &mut Ui, status: &JobStatus, format_position: impl Fn(usize) -> String) {
+    match &status.progress {
+        Some(progress) => {
+            if let Some(fraction) = progress.fraction() {
+                ui.add(egui::ProgressBar::new(fraction).show_percentage());
+            }
+            ui.label(format!("Processed: {}", format_progress(progress)));
+        },
+        None => { ui.label("Starting..."); },
+    }
+    if let Some(matched) = status.matched {
+        ui.label(format!("Match at {}", format_position(matched)));
+    }
+    for warning in status.warnings.iter().rev().take(3) {
+        ui.colored_label(egui::Color32::YELLOW, warning);
+    }
+    if let Some(err) = &status.error {
+        ui.colored_label(egui::Color32::RED, err);
+    }
+}
+
+fn parse_window_query(mut query: WindowQuery, value: &str) -> Result<WindowQuery, String> {
+    let value = value.trim();
+    query.condition = match query.condition {
+        WindowCondition::DigitSum(_) => WindowCondition::DigitSum(value.parse().map_err(|_| format!("The digit sum must be a whole number, got \"{value}\""))?),
+        WindowCondition::Prime => WindowCondition::Prime,
+        WindowCondition::DivisibleBy(_) => WindowCondition::DivisibleBy(value.parse().map_err(|_| format!("The divisor must be a whole number, got \"{value}\""))?),
+    };
+    query.validate()?;
+    Ok(query)
+}
+
+fn optional_run(ui: &mut Ui, label: &str, run: &mut Option<usize>, default: usize) {
+    ui.horizontal(|ui| {
+        let mut enabled = run.is_some();
+        if ui.checkbox(&mut enabled, label).changed() {
+            *run = enabled.then_some(default);
+        }
+        if let Some(run) = run {
+            ui.add(egui::DragValue::new(run).clamp_range(1usize..=100usize));
+        }
+    });
+}
+
+// Table of what an analysis found, returns the finding to show in the viewer
+fn show_findings(ui: &mut Ui, job: &Job, convention: PositionConvention, integer_digits: usize) -> Option<usize> {
+    let findings = &job.status.findings;
+    if findings.is_empty() {
+        if job.state == JobState::Finished && matches!(job.kind, JobKind::Analysis { .. }) {
+            ui.label("Nothing found");
+        }
+        return None;
+    }
+    ui.label(format!("Findings: {}", findings.len()));
+    if let JobKind::Analysis { analysis, .. } = &job.kind {
+        if let Some(counted) = analysis.convention().filter(|&counted| counted != convention) {
+            ui.label(format!("Looked for with positions counted {}", counted.name()));
+        }
+    }
+
+    let mut show = None;
+    egui::ScrollArea::vertical().id_source("findings_scroll").max_height(300f32).show(ui, |ui| {
+        egui::Grid::new("findings_grid").striped(true).show(ui, |ui| {
+            for (i, finding) in findings.iter().enumerate() {
+                ui.label(finding.label.as_str());
+                ui.label(convention.format(finding.position, integer_digits));
+                if finding.digits.len() > 30 {
+                    ui.monospace(format!("{}... ({} digits)", &finding.digits[..30], finding.digits.len()));
+                }
+                else {
+                    ui.monospace(finding.digits.as_str());
+                }
+                if ui.small_button("Show").clicked() {
+                    show = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+    });
+    show
+}
+
+// How lucky a finished search was, or when a running one should find its match
+fn show_job_estimate(ui: &mut Ui, job: &Job, format_position: impl Fn(f64) -> String) {
+    let (pattern, start) = match (job.kind.pattern(), job.search_start()) {
+        (Some(pattern), Some(start)) => (pattern, start),
+        _ => return,
+    };
+    let estimate = PatternEstimate::new(pattern);
+    if let Some(index) = job.status.matched {
+        let luck = estimate.luck(index.saturating_sub(start));
+        ui.label(format!("{}: {} of random digit sequences have it this early", luck_name(luck), format_probability(luck)));
+    }
+    else if let Some((searched, _)) = &job.status.not_found {
+        let luck = estimate.probability_within(searched.len());
+        ui.label(format!("{}: {} of random digit sequences have it in that range", luck_name(luck), format_probability(luck)));
+    }
+    else if let (JobKind::Search { .. }, JobState::Running, Some(progress)) = (&job.kind, job.state, &job.status.progress) {
+        let searched = progress.done.saturating_sub(start);
+        let expected = start as f64 + estimate.expected_position_after(searched);
+        let mut text = format!("Expected around {}", format_position(expected));
+        if progress.rate > 0f64 {
+            let left = (expected - progress.done as f64).max(0f64) / progress.rate;
+            let eta = std::time::Duration::try_from_secs_f64(left).map_or("—".to_string(), format_duration);
+            text.push_str(format!(", in about {eta}").as_str());
+        }
+        ui.label(text);
+    }
+}
+
+fn format_expected(position: f64, convention: PositionConvention, integer_digits: usize) -> String {
+    // past what fits in a cache, the exact digits don't mean much
+    if position < 1e15 {
+        convention.format(position.round() as usize, integer_digits)
+    }
+    else {
+        format!("{position:.2e}")
+    }
+}
+
+fn format_probability(probability: f64) -> String {
+    match probability * 100f64 {
+        p if p > 0f64 && p < 0.01 => "<0.01%".to_string(),
+        p if p < 100f64 && p > 99.99 => ">99.99%".to_string(),
+        p => format!("{}%", (p * 100f64).round() / 100f64),
+    }
 }
 
 impl epi::App for TemplateApp {
@@ -317,18 +1487,38 @@ impl epi::App for TemplateApp {
         _ctx: &egui::CtxRef,
         _frame: &epi::Frame,
         _storage: Option<&dyn epi::Storage>,
-    ) {}
+    ) {
+        #[cfg(feature = "persistence")]
+        if let Some(saved) = _storage.and_then(|storage| epi::get_value(storage, epi::APP_KEY)) {
+            self.restore(saved);
+        }
+        #[cfg(target_arch = "wasm32")]
+        self.load_digits();
+
+        // the cache file, then chunks downloaded by preloads that weren't written to it yet
+        #[cfg(not(target_arch = "wasm32"))]
+        self.switch_cache();
+    }
+
+    #[cfg(feature = "persistence")]
+    fn save(&mut self, storage: &mut dyn epi::Storage) {
+        self.config = self.search.get_config().clone();
+        epi::set_value(storage, epi::APP_KEY, self);
+    }
 
     fn update(&mut self, ctx: &egui::CtxRef, _: &epi::Frame) {
         ctx.request_repaint();
 
+        self.update_jobs();
+
+        self.show_jobs(ctx);
+        self.show_history(ctx);
+        self.show_plots(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.show_file_dialog(ctx);
+
         egui::CentralPanel::default().show(ctx, |ui| {
-            match &self.state {
-                AppState::Input(_) => self.input_state(ui),
-                AppState::Preload(_) => self.preload_state(ui),
-                AppState::Search(_) => self.search_state(ui),
-                AppState::Found(_) => self.found_state(ui),
-            }
+            self.main_view(ui);
         });
     }
 }
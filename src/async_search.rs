@@ -0,0 +1,385 @@
+use futures::{channel::mpsc::{self, UnboundedReceiver, UnboundedSender}, stream, Future, StreamExt};
+use reqwest::Client;
+use instant::Instant;
+use std::{ops::Range, sync::{Arc, Mutex}};
+
+use crate::{analysis::*, api::*, config::{SearchConfig, MAX_WORKERS}, estimate::*, job::*, limits::AsyncRequestLimiter};
+
+// Async counterpart of `Search`.
+// Jobs are plain futures: they can run concurrently on one runtime and are cancelled by dropping them.
+// Progress is reported through an unbounded stream of `JobEvent`s returned next to the future.
+// All jobs share one limiter for `max_in_flight` and `requests_per_second`, as with `Search`;
+// a preload keeps up to `workers` requests going, like its threads.
+#[derive(Clone)]
+pub struct AsyncSearch {
+    config: SearchConfig,
+    client: Client,
+    limiter: Arc<AsyncRequestLimiter>, // replaced with the config
+
+    saved_digits: Arc<Mutex<String>>,
+}
+
+async fn get_digits(client: &Client, config: &SearchConfig, start: usize, number_of_digits: usize) -> Result<(String, usize), Box<dyn std::error::Error + Send + Sync>> {
+    let response = client.get(config.digits_url(start, number_of_digits)).send().await?;
+    let status = response.status().as_u16();
+    let text = response.text().await?;
+    Ok((parse_digits(status, text.as_str(), number_of_digits)?, text.len()))
+}
+
+async fn fetch_digits(client: &Client, limiter: &AsyncRequestLimiter, config: &SearchConfig, start: usize, number_of_digits: usize, events: &UnboundedSender<JobEvent>) -> Result<(String, usize), String> {
+    let mut attempt = 1;
+    loop {
+        let permit = limiter.acquire().await;
+        let res = get_digits(client, config, start, number_of_digits).await;
+        drop(permit);
+        match res {
+            Ok(res) => return Ok(res),
+            Err(err) if attempt < MAX_REQUEST_ATTEMPTS => {
+                let _ = events.unbounded_send(JobEvent::Warning(format!("Request for digits {start}-{} failed (attempt {attempt}): {err}", start + number_of_digits)));
+                attempt += 1;
+            },
+            Err(err) => return Err(format!("Request for digits {start}-{} failed: {err}", start + number_of_digits)),
+        }
+    }
+}
+
+// Appends the part of a chunk the cache doesn't have yet, so concurrent jobs can't duplicate digits.
+// Returns false if the chunk starts past the end of the cache.
+fn append_chunk(digits: &mut String, start: usize, chunk: &str) -> bool {
+    let len = digits.len();
+    if start > len {
+        return false;
+    }
+    if start + chunk.len() > len {
+        digits.push_str(&chunk[len - start..]);
+    }
+    true
+}
+
+impl Default for AsyncSearch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AsyncSearch {
+    pub fn new() -> Self {
+        Self::with_digits(Arc::default())
+    }
+
+    // Shares the digit cache, e.g. with a blocking `Search`
+    pub fn with_digits(saved_digits: Arc<Mutex<String>>) -> Self {
+        Self::with_config(SearchConfig::default(), saved_digits).unwrap()
+    }
+
+    pub fn with_config(config: SearchConfig, saved_digits: Arc<Mutex<String>>) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            client: config.build_client()?,
+            limiter: Arc::new(AsyncRequestLimiter::from_config(&config)),
+            config,
+            saved_digits,
+        })
+    }
+
+    pub fn get_config(&self) -> &SearchConfig {
+        &self.config
+    }
+
+    // Applies to jobs started afterwards
+    pub fn set_config(&mut self, config: SearchConfig) -> Result<(), Box<dyn std::error::Error>> {
+        config.validate()?;
+        self.client = config.build_client()?;
+        self.limiter = Arc::new(AsyncRequestLimiter::from_config(&config));
+        self.config = config;
+        Ok(())
+    }
+
+    pub fn get_digits(&self) -> Arc<Mutex<String>> {
+        self.saved_digits.clone()
+    }
+    pub fn digits_loaded(&self) -> usize {
+        self.saved_digits.lock().unwrap().len()
+    }
+
+    // Expected position of the pattern and the chances to find it in the cache or the bounds.
+    // `rate` is the download speed in digits/s, for the ETA.
+    pub fn estimate(&self, search_for: &str, bounds: &SearchBounds, rate: Option<f64>) -> SearchEstimate {
+        estimate_search(search_for, bounds, self.digits_loaded(), rate)
+    }
+
+    pub fn set_digits_per_request(&mut self, digits_per_request: usize) {
+        self.config.digits_per_request = digits_per_request.clamp(1usize, MAX_DIGITS_PER_REQUEST);
+    }
+
+    pub fn set_workers(&mut self, workers: usize) {
+        self.config.workers = workers.clamp(1usize, MAX_WORKERS);
+    }
+
+    pub fn set_max_concurrent_requests(&mut self, max_concurrent_requests: usize) {
+        self.config.max_in_flight = max_concurrent_requests.max(1);
+        self.limiter = Arc::new(AsyncRequestLimiter::from_config(&self.config));
+    }
+
+    // Returns `count` digits starting at `start`, from the cache when possible
+    pub fn lookup(&self, start: usize, count: usize) -> impl Future<Output = Result<String, String>> + 'static {
+        let this = self.clone();
+        async move {
+            {
+                let digits = this.saved_digits.lock().unwrap();
+                if start + count <= digits.len() {
+                    return Ok(digits[start..start + count].to_string());
+                }
+            }
+
+            let (ev_tx, _ev_rx) = mpsc::unbounded();
+            let requests = (start..start + count)
+                .step_by(this.config.digits_per_request)
+                .map(|s| (s, this.config.digits_per_request.min(start + count - s)));
+
+            let mut chunks = stream::iter(requests)
+                .map(|(s, n)| {
+                    let client = &this.client;
+                    let limiter = &*this.limiter;
+                    let config = &this.config;
+                    let ev_tx = &ev_tx;
+                    async move { fetch_digits(client, limiter, config, s, n, ev_tx).await }
+                })
+                .buffered(this.config.max_in_flight);
+
+            let mut result = String::with_capacity(count);
+            while let Some(chunk) = chunks.next().await {
+                result.push_str(chunk?.0.as_str());
+            }
+            Ok(result)
+        }
+    }
+
+    // Loads digits into the cache until it holds `count` digits.
+    // Chunks are requested concurrently but appended in order, so cancelling keeps the cache consistent.
+    pub fn preload(&self, count: usize) -> (impl Future<Output = Result<(), String>> + 'static, UnboundedReceiver<JobEvent>) {
+        let (ev_tx, ev_rx) = mpsc::unbounded();
+        let this = self.clone();
+
+        let job = async move {
+            let len = this.digits_loaded();
+            let _ = ev_tx.unbounded_send(JobEvent::Started { total: Some(count) });
+            let mut meter = ProgressMeter::new(len, Some(count));
+
+            let requests = (len..count.max(len))
+                .step_by(this.config.digits_per_request)
+                .map(|s| (s, this.config.digits_per_request.min(count - s)));
+
+            let mut chunks = stream::iter(requests)
+                .map(|(s, n)| {
+                    let client = &this.client;
+                    let limiter = &*this.limiter;
+                    let config = &this.config;
+                    let ev_tx = &ev_tx;
+                    async move { (s, fetch_digits(client, limiter, config, s, n, ev_tx).await) }
+                })
+                .buffered(this.config.workers);
+
+            while let Some((start, chunk)) = chunks.next().await {
+                match chunk {
+                    Ok((new_digits, bytes)) => {
+                        meter.add_bytes(bytes);
+                        let loaded = {
+                            let mut digits = this.saved_digits.lock().unwrap();
+                            append_chunk(&mut digits, start, new_digits.as_str());
+                            digits.len()
+                        };
+                        let _ = ev_tx.unbounded_send(meter.event(loaded));
+                    },
+                    Err(err) => {
+                        let _ = ev_tx.unbounded_send(JobEvent::Error(err.clone()));
+                        return Err(err);
+                    },
+                }
+            }
+
+            let _ = ev_tx.unbounded_send(meter.event(this.digits_loaded()));
+            let _ = ev_tx.unbounded_send(JobEvent::Finished);
+            Ok(())
+        };
+
+        (job, ev_rx)
+    }
+
+    // Searches the cache, then keeps fetching (and caching) new digits until `search_for` is found
+    pub fn search(&self, search_for: &str) -> (impl Future<Output = Result<SearchOutcome, String>> + 'static, UnboundedReceiver<JobEvent>) {
+        self.search_in(search_for, SearchBounds::default())
+    }
+
+    // Continues after a match at `index`, through the cache first and then the network
+    pub fn find_next(&self, search_for: &str, index: usize) -> (impl Future<Output = Result<SearchOutcome, String>> + 'static, UnboundedReceiver<JobEvent>) {
+        self.search_in(search_for, SearchBounds::from(index + 1))
+    }
+
+    // Steps back from a match at `index`, only through the cache
+    pub fn find_previous(&self, search_for: &str, index: usize) -> Option<usize> {
+        find_previous(self.saved_digits.lock().unwrap().as_str(), search_for, index)
+    }
+
+    // Like `search`, but only matches starting inside `bounds` count.
+    // Digits fetched past the end of the cache are only kept if they continue it.
+    pub fn search_in(&self, search_for: &str, bounds: SearchBounds) -> (impl Future<Output = Result<SearchOutcome, String>> + 'static, UnboundedReceiver<JobEvent>) {
+        let (ev_tx, ev_rx) = mpsc::unbounded();
+        let this = self.clone();
+        let search_for = search_for.to_string();
+
+        let job = async move {
+            let started = Instant::now();
+            let total = bounds.scan_end(search_for.len());
+            let scan_end = total.unwrap_or(usize::MAX);
+            let _ = ev_tx.unbounded_send(JobEvent::Started { total });
+            let not_found = |searched: Range<usize>, reason: StopReason| {
+                let _ = ev_tx.unbounded_send(JobEvent::NotFound { searched: searched.clone(), reason });
+                let _ = ev_tx.unbounded_send(JobEvent::Finished);
+                Ok(SearchOutcome::NotFound { searched, reason })
+            };
+
+            let (ind, len, mut tail) = {
+                let digits = this.saved_digits.lock().unwrap();
+                let end = scan_end.min(digits.len());
+                let start = bounds.start.min(end);
+                // the tail only helps if the fetched digits continue it
+                let tail_start = if bounds.start <= end { end.saturating_sub(search_for.len().saturating_sub(1)).max(start) } else { end };
+                (digits[start..end].find(search_for.as_str()).map(|i| start + i), end.max(bounds.start), digits[tail_start..end].to_string())
+            };
+            if let Some(ind) = ind {
+                let _ = ev_tx.unbounded_send(ProgressMeter::new(bounds.start, total).event(ind + search_for.len()));
+                let _ = ev_tx.unbounded_send(JobEvent::Match(ind));
+                let _ = ev_tx.unbounded_send(JobEvent::Finished);
+                return Ok(SearchOutcome::Found(ind));
+            }
+
+            let mut meter = ProgressMeter::new(bounds.start, total);
+            let _ = ev_tx.unbounded_send(meter.event(len));
+            if len >= scan_end {
+                return not_found(bounds.searched(len, search_for.len()), bounds.end_reason());
+            }
+
+            let digits_per_request = this.config.digits_per_request;
+            let mut chunks = stream::iter((len..scan_end).step_by(digits_per_request))
+                .map(|s| {
+                    let client = &this.client;
+                    let limiter = &*this.limiter;
+                    let config = &this.config;
+                    let ev_tx = &ev_tx;
+                    let n = digits_per_request.min(scan_end - s);
+                    async move { fetch_digits(client, limiter, config, s, n, ev_tx).await }
+                })
+                .buffered(this.config.max_in_flight);
+
+            let mut digit = len;
+            while let Some(chunk) = chunks.next().await {
+                let new_digits = match chunk {
+                    Ok((new_digits, bytes)) => {
+                        meter.add_bytes(bytes);
+                        new_digits
+                    },
+                    Err(err) => {
+                        let _ = ev_tx.unbounded_send(JobEvent::Error(err.clone()));
+                        return Err(err);
+                    },
+                };
+                append_chunk(&mut this.saved_digits.lock().unwrap(), digit, new_digits.as_str());
+
+                let mut d = std::mem::take(&mut tail);
+                d.push_str(new_digits.as_str());
+                let d_start = digit + new_digits.len() - d.len();
+                digit += new_digits.len();
+
+                let _ = ev_tx.unbounded_send(meter.event(digit));
+
+                if let Some(ind) = d.find(search_for.as_str()) {
+                    let _ = ev_tx.unbounded_send(JobEvent::Match(d_start + ind));
+                    let _ = ev_tx.unbounded_send(JobEvent::Finished);
+                    return Ok(SearchOutcome::Found(d_start + ind));
+                }
+                if bounds.timed_out(started) {
+                    return not_found(bounds.searched(digit, search_for.len()), StopReason::TimeLimit);
+                }
+                tail = d[d.len().saturating_sub(search_for.len().saturating_sub(1))..].to_string();
+            }
+
+            not_found(bounds.searched(digit, search_for.len()), bounds.end_reason())
+        };
+
+        (job, ev_rx)
+    }
+
+    // Feeds the digits of `bounds` to the analyzer: the cached ones, then fetched ones.
+    // Without an end it runs until it's dropped.
+    pub fn analyze(&self, mut analyzer: Box<dyn Analyzer>, bounds: SearchBounds) -> (impl Future<Output = Result<(), String>> + 'static, UnboundedReceiver<JobEvent>) {
+        let (ev_tx, ev_rx) = mpsc::unbounded();
+        let this = self.clone();
+
+        let job = async move {
+            let started = Instant::now();
+            let scan_end = bounds.limit().unwrap_or(usize::MAX);
+            let _ = ev_tx.unbounded_send(JobEvent::Started { total: bounds.limit() });
+            let send = |event| ev_tx.unbounded_send(event).is_ok();
+            let mut meter = ProgressMeter::new(bounds.start, bounds.limit());
+            let mut findings = Vec::new();
+            let mut reported = 0usize;
+
+            let len = {
+                let digits = this.saved_digits.lock().unwrap();
+                let end = scan_end.min(digits.len());
+                if bounds.start < end {
+                    analyzer.feed(&digits[bounds.start..end], bounds.start, &mut findings);
+                }
+                end.max(bounds.start)
+            };
+            report_findings(&mut findings, &mut reported, send);
+            let _ = ev_tx.unbounded_send(meter.event(len));
+
+            let digits_per_request = this.config.digits_per_request;
+            let mut chunks = stream::iter((len..scan_end).step_by(digits_per_request))
+                .map(|s| {
+                    let client = &this.client;
+                    let limiter = &*this.limiter;
+                    let config = &this.config;
+                    let ev_tx = &ev_tx;
+                    let n = digits_per_request.min(scan_end - s);
+                    async move { fetch_digits(client, limiter, config, s, n, ev_tx).await }
+                })
+                .buffered(this.config.max_in_flight);
+
+            let mut digit = len;
+            while digit < scan_end && !analyzer.done() {
+                if bounds.timed_out(started) {
+                    let _ = ev_tx.unbounded_send(JobEvent::Warning(format!("Stopped by the time limit after {} digits", digit - bounds.start)));
+                    break;
+                }
+                let new_digits = match chunks.next().await {
+                    Some(Ok((new_digits, bytes))) => {
+                        meter.add_bytes(bytes);
+                        new_digits
+                    },
+                    Some(Err(err)) => {
+                        let _ = ev_tx.unbounded_send(JobEvent::Error(err.clone()));
+                        return Err(err);
+                    },
+                    None => break,
+                };
+                append_chunk(&mut this.saved_digits.lock().unwrap(), digit, new_digits.as_str());
+                analyzer.feed(new_digits.as_str(), digit, &mut findings);
+                digit += new_digits.len();
+                report_findings(&mut findings, &mut reported, send);
+                let _ = ev_tx.unbounded_send(meter.event(digit));
+            }
+
+            analyzer.finish(digit, &mut findings);
+            report_findings(&mut findings, &mut reported, send);
+            if let Some(warning) = unreported_warning(reported) {
+                let _ = ev_tx.unbounded_send(warning);
+            }
+            let _ = ev_tx.unbounded_send(JobEvent::Finished);
+            Ok(())
+        };
+
+        (job, ev_rx)
+    }
+}
@@ -0,0 +1,596 @@
+use std::{fmt, ops::Range};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs::{File, OpenOptions}, io::{self, Read, Seek, SeekFrom, Write}, path::Path};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::caches::SavedFile;
+
+// Binary digit cache, all numbers little-endian:
+//
+//   magic "PIDIGITS", version u16, header length u32
+//   two header slots of that length, each:
+//     generation u64
+//     radix u8, integer digits u32, block digits u32, total digits u64, created u64 (unix seconds)
+//     constant, source: u16 length + UTF-8
+//     covered ranges: u32 count + (start u64, end u64) each
+//     block checksums: u32 slots + CRC-32 of each block's bytes, 0 in the slots past the last block
+//     header checksum: CRC-32 of everything above in the slot
+//   blocks
+//
+// The valid slot with the higher generation describes the file. Generation g is written to slot
+// g % 2, so rewriting the header never touches the one in use, a torn write leaves it readable.
+//
+// Every three digits are packed into 10 bits (0..=999), a trailing group of one or two digits
+// takes 10 bits too. All blocks but the last hold `block_digits` digits and have the same size,
+// so any block can be read on its own. The spare checksum slots let blocks be appended without
+// moving the ones already written.
+
+pub const MAGIC: &[u8; 8] = b"PIDIGITS";
+pub const VERSION: u16 = 1;
+pub const FILE_EXTENSION: &str = "pidc";
+pub const BLOCK_DIGITS: usize = 3 * 65536;
+#[cfg(not(target_arch = "wasm32"))]
+const PREAMBLE_LEN: usize = MAGIC.len() + 2 + 4;
+
+#[derive(Debug)]
+pub enum CacheError {
+    #[cfg(not(target_arch = "wasm32"))]
+    Io(io::Error),
+    Format(String),
+    Checksum { block: Option<usize> }, // None for the header
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            CacheError::Io(err) => write!(f, "{err}"),
+            CacheError::Format(message) => write!(f, "Invalid cache file: {message}"),
+            CacheError::Checksum { block: Some(block) } => write!(f, "Block {block} of the cache file is corrupt"),
+            CacheError::Checksum { block: None } => write!(f, "The cache file header is corrupt"),
+        }
+    }
+}
+
+impl std::error::Error for CacheError {}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<io::Error> for CacheError {
+    fn from(err: io::Error) -> Self {
+        CacheError::Io(err)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CacheHeader {
+    pub radix: u8,
+    pub integer_digits: usize,
+    pub block_digits: usize,
+    pub total_digits: usize,
+    pub created: u64,
+    pub constant: String,
+    pub source: String,
+    pub ranges: Vec<Range<usize>>,
+    pub checksums: Vec<u32>,
+    pub capacity: usize, // checksum slots, blocks can be appended until they're used
+    pub generation: u64, // counts header rewrites
+}
+
+impl CacheHeader {
+    pub fn blocks(&self) -> usize {
+        self.checksums.len()
+    }
+
+    pub fn block_range(&self, block: usize) -> Range<usize> {
+        let start = block * self.block_digits;
+        start..(start + self.block_digits).min(self.total_digits)
+    }
+
+    fn block_bytes(&self) -> usize {
+        packed_len(self.block_digits)
+    }
+
+    // A new file has the same header in both slots
+    fn encode(&self) -> Vec<u8> {
+        let slot = self.encode_slot();
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(slot.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&slot);
+        bytes.extend_from_slice(&slot);
+        bytes
+    }
+
+    fn encode_slot(&self) -> Vec<u8> {
+        let mut bytes = self.generation.to_le_bytes().to_vec();
+        bytes.push(self.radix);
+        bytes.extend_from_slice(&(self.integer_digits as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.block_digits as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.total_digits as u64).to_le_bytes());
+        bytes.extend_from_slice(&self.created.to_le_bytes());
+        for text in [&self.constant, &self.source] {
+            bytes.extend_from_slice(&(text.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(text.as_bytes());
+        }
+        bytes.extend_from_slice(&(self.ranges.len() as u32).to_le_bytes());
+        for range in &self.ranges {
+            bytes.extend_from_slice(&(range.start as u64).to_le_bytes());
+            bytes.extend_from_slice(&(range.end as u64).to_le_bytes());
+        }
+        bytes.extend_from_slice(&(self.capacity as u32).to_le_bytes());
+        for slot in 0..self.capacity {
+            bytes.extend_from_slice(&self.checksums.get(slot).copied().unwrap_or(0u32).to_le_bytes());
+        }
+        let checksum = crc32(&bytes);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+        bytes
+    }
+
+    // Where the slot for `generation` starts
+    #[cfg(not(target_arch = "wasm32"))]
+    fn slot_offset(generation: u64, slot_len: usize) -> usize {
+        PREAMBLE_LEN + (generation % 2) as usize * slot_len
+    }
+
+    // Returns the header and where the blocks start, `Ok(None)` if `bytes` ends inside the header
+    fn decode(bytes: &[u8]) -> Result<Option<(Self, usize)>, CacheError> {
+        let mut reader = ByteReader { bytes, pos: 0 };
+        let slot_len = match Self::decode_preamble(&mut reader) {
+            Some(slot_len) => slot_len?,
+            None => return Ok(None),
+        };
+        let slots = match reader.take(2 * slot_len) {
+            Some(slots) => slots,
+            None => return Ok(None),
+        };
+        let (first, second) = slots.split_at(slot_len);
+        let header = match (Self::decode_slot(first), Self::decode_slot(second)) {
+            (Ok(first), Ok(second)) => if second.generation > first.generation { second } else { first },
+            (Ok(header), Err(_)) | (Err(_), Ok(header)) => header,
+            (Err(err), Err(_)) => return Err(err),
+        };
+        Ok(Some((header, reader.pos)))
+    }
+
+    // Returns the length of a header slot
+    fn decode_preamble(reader: &mut ByteReader<'_>) -> Option<Result<usize, CacheError>> {
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Some(Err(CacheError::Format("not a binary digit cache".to_string())));
+        }
+        let version = u16::from_le_bytes(reader.take(2)?.try_into().unwrap());
+        if version != VERSION {
+            return Some(Err(CacheError::Format(format!("unsupported version {version}"))));
+        }
+        Some(Ok(reader.u32()? as usize))
+    }
+
+    fn decode_slot(bytes: &[u8]) -> Result<Self, CacheError> {
+        let end = match bytes.len().checked_sub(4) {
+            Some(end) => end,
+            None => return Err(CacheError::Format("truncated header".to_string())),
+        };
+        if crc32(&bytes[..end]) != u32::from_le_bytes(bytes[end..].try_into().unwrap()) {
+            return Err(CacheError::Checksum { block: None });
+        }
+        let mut reader = ByteReader { bytes: &bytes[..end], pos: 0 };
+        match Self::decode_fields(&mut reader) {
+            Some(Ok(header)) if reader.pos == end => Ok(header),
+            Some(Err(err)) => Err(err),
+            _ => Err(CacheError::Format("inconsistent header length".to_string())),
+        }
+    }
+
+    fn decode_fields(reader: &mut ByteReader<'_>) -> Option<Result<Self, CacheError>> {
+        let generation = reader.u64()?;
+        let radix = reader.take(1)?[0];
+        let integer_digits = reader.u32()? as usize;
+        let block_digits = reader.u32()? as usize;
+        let total_digits = reader.u64()? as usize;
+        let created = reader.u64()?;
+        let constant = reader.text()?;
+        let source = reader.text()?;
+        let mut ranges = Vec::new();
+        for _ in 0..reader.u32()? {
+            ranges.push(reader.u64()? as usize..reader.u64()? as usize);
+        }
+        let capacity = reader.u32()? as usize;
+        let mut checksums = Vec::new();
+        for _ in 0..capacity {
+            checksums.push(reader.u32()?);
+        }
+
+        if radix != 10 {
+            return Some(Err(CacheError::Format(format!("radix {radix} isn't supported"))));
+        }
+        let blocks = if block_digits == 0 { 0 } else { total_digits.div_ceil(block_digits) };
+        if block_digits == 0 || block_digits % 3 != 0 || capacity < blocks {
+            return Some(Err(CacheError::Format("inconsistent block layout".to_string())));
+        }
+        checksums.truncate(blocks);
+        Some(Ok(Self { radix, integer_digits, block_digits, total_digits, created, constant, source, ranges, checksums, capacity, generation }))
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.bytes.get(self.pos..self.pos + n)?;
+        self.pos += n;
+        Some(bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn text(&mut self) -> Option<String> {
+        let len = u16::from_le_bytes(self.take(2)?.try_into().unwrap()) as usize;
+        Some(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+}
+
+// CRC-32 of every byte value, built at compile time
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(0xffff_ffffu32, |crc, &b| (crc >> 8) ^ CRC32_TABLE[((crc ^ b as u32) & 0xff) as usize])
+}
+
+fn packed_len(digits: usize) -> usize {
+    (digits.div_ceil(3) * 10).div_ceil(8)
+}
+
+fn pack(digits: &str) -> Vec<u8> {
+    let mut bytes = vec![0u8; packed_len(digits.len())];
+    for (group, chunk) in digits.as_bytes().chunks(3).enumerate() {
+        let value = chunk.iter().fold(0u32, |value, &b| value * 10 + (b - b'0') as u32);
+        let bit = group * 10;
+        for i in 0..10 {
+            if value & (1 << i) != 0 {
+                bytes[(bit + i) / 8] |= 1 << ((bit + i) % 8);
+            }
+        }
+    }
+    bytes
+}
+
+fn unpack(bytes: &[u8], digits: usize) -> Result<String, CacheError> {
+    let mut text = String::with_capacity(digits);
+    for group in 0..digits.div_ceil(3) {
+        let bit = group * 10;
+        let mut value = 0u32;
+        for i in 0..10 {
+            if bytes[(bit + i) / 8] & (1 << ((bit + i) % 8)) != 0 {
+                value |= 1 << i;
+            }
+        }
+        let width = (digits - group * 3).min(3);
+        if value >= 10u32.pow(width as u32) {
+            return Err(CacheError::Format(format!("invalid digit group at {}", group * 3)));
+        }
+        text.push_str(format!("{value:0width$}").as_str());
+    }
+    Ok(text)
+}
+
+pub struct CacheMetadata<'a> {
+    pub integer_digits: usize,
+    pub constant: &'a str,
+    pub source: &'a str,
+    pub created: u64,
+}
+
+pub fn encode(digits: &str, metadata: &CacheMetadata<'_>) -> Vec<u8> {
+    let blocks: Vec<Vec<u8>> = digits.as_bytes().chunks(BLOCK_DIGITS).map(|block| pack(std::str::from_utf8(block).unwrap())).collect();
+    let header = CacheHeader {
+        radix: 10,
+        integer_digits: metadata.integer_digits,
+        block_digits: BLOCK_DIGITS,
+        total_digits: digits.len(),
+        created: metadata.created,
+        constant: metadata.constant.to_string(),
+        source: metadata.source.to_string(),
+        ranges: (!digits.is_empty()).then_some(0..digits.len()).into_iter().collect(),
+        checksums: blocks.iter().map(|block| crc32(block)).collect(),
+        capacity: (blocks.len() + 1).next_power_of_two().max(64),
+        generation: 0u64,
+    };
+    let mut bytes = header.encode();
+    for block in blocks {
+        bytes.extend_from_slice(&block);
+    }
+    bytes
+}
+
+pub fn is_binary_cache(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+// Digits of a whole file in memory; `complete` is false when `bytes` is only the start of a file
+pub fn decode(bytes: &[u8], complete: bool) -> Result<(CacheHeader, String), CacheError> {
+    let (header, data_start) = match CacheHeader::decode(bytes)? {
+        Some(header) => header,
+        None => return Err(CacheError::Format("truncated header".to_string())),
+    };
+    let mut digits = String::with_capacity(header.total_digits.min(bytes.len() * 12 / 5));
+    for block in 0..header.blocks() {
+        let range = header.block_range(block);
+        let start = data_start + block * header.block_bytes();
+        let block_bytes = match bytes.get(start..start + packed_len(range.len())) {
+            Some(block_bytes) => block_bytes,
+            None if !complete => break,
+            None => return Err(CacheError::Format(format!("block {block} is missing"))),
+        };
+        digits.push_str(read_block(&header, block, block_bytes)?.as_str());
+    }
+    Ok((header, digits))
+}
+
+fn read_block(header: &CacheHeader, block: usize, bytes: &[u8]) -> Result<String, CacheError> {
+    if crc32(bytes) != header.checksums[block] {
+        return Err(CacheError::Checksum { block: Some(block) });
+    }
+    unpack(bytes, header.block_range(block).len())
+}
+
+// Returns the length of the file
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_cache(path: &Path, digits: &str, metadata: &CacheMetadata<'_>) -> io::Result<u64> {
+    let bytes = encode(digits, metadata);
+    crate::caches::write_atomic(path, &bytes)?;
+    Ok(bytes.len() as u64)
+}
+
+// Appends `digits` to a cache file that is still exactly as recorded, ends with a whole block and
+// has checksum slots left for the new ones. The blocks are written first, then the header goes to
+// the slot that isn't in use, until it's complete the old header still describes the file.
+// Returns the new length, None if it can't be appended to.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn append_cache(saved: &SavedFile, digits: &str) -> Result<Option<u64>, CacheError> {
+    let mut file = match OpenOptions::new().read(true).write(true).open(&saved.path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    if file.metadata()?.len() != saved.len {
+        return Ok(None);
+    }
+    let (mut header, data_start) = read_header(&mut file)?;
+    let end = data_start + (header.blocks() * header.block_bytes()) as u64;
+    let blocks = digits.len().div_ceil(header.block_digits);
+    if header.total_digits != saved.digits || header.total_digits % header.block_digits != 0 || end != saved.len
+        || header.blocks() + blocks > header.capacity || header.ranges.len() != 1 || header.ranges[0] != (0..header.total_digits) {
+        return Ok(None);
+    }
+    if digits.is_empty() {
+        return Ok(Some(saved.len));
+    }
+
+    let mut len = end;
+    file.seek(SeekFrom::Start(end))?;
+    for block in digits.as_bytes().chunks(header.block_digits) {
+        let bytes = pack(std::str::from_utf8(block).unwrap());
+        file.write_all(&bytes)?;
+        header.checksums.push(crc32(&bytes));
+        len += bytes.len() as u64;
+    }
+    file.sync_data()?;
+
+    // same length as before, only the counts and the used checksum slots change
+    let slot_len = (data_start as usize - PREAMBLE_LEN) / 2;
+    header.total_digits += digits.len();
+    header.ranges[0].end = header.total_digits;
+    header.generation += 1;
+    file.seek(SeekFrom::Start(CacheHeader::slot_offset(header.generation, slot_len) as u64))?;
+    file.write_all(&header.encode_slot())?;
+    file.sync_all()?;
+    Ok(Some(len))
+}
+
+// The header grows with the number of blocks, reads until it's complete.
+// Returns the header and where the blocks start.
+#[cfg(not(target_arch = "wasm32"))]
+fn read_header(file: &mut File) -> Result<(CacheHeader, u64), CacheError> {
+    let mut bytes = Vec::new();
+    loop {
+        let len = bytes.len();
+        let read = (&mut *file).take(len.max(4096) as u64).read_to_end(&mut bytes)?;
+        if let Some((header, data_start)) = CacheHeader::decode(&bytes)? {
+            return Ok((header, data_start as u64));
+        }
+        if read == 0 {
+            return Err(CacheError::Format("truncated header".to_string()));
+        }
+    }
+}
+
+// Reads single blocks of a cache file without loading the rest of it
+#[cfg(not(target_arch = "wasm32"))]
+pub struct CacheReader {
+    file: File,
+    header: CacheHeader,
+    data_start: u64,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl CacheReader {
+    pub fn open(path: &Path) -> Result<Self, CacheError> {
+        let mut file = File::open(path)?;
+        let (header, data_start) = read_header(&mut file)?;
+        Ok(Self { file, header, data_start })
+    }
+
+    pub fn header(&self) -> &CacheHeader {
+        &self.header
+    }
+
+    pub fn read_block(&mut self, block: usize) -> Result<String, CacheError> {
+        if block >= self.header.blocks() {
+            return Err(CacheError::Format(format!("there is no block {block}")));
+        }
+        let mut bytes = vec![0u8; packed_len(self.header.block_range(block).len())];
+        self.file.seek(SeekFrom::Start(self.data_start + (block * self.header.block_bytes()) as u64))?;
+        self.file.read_exact(&mut bytes)?;
+        read_block(&self.header, block, &bytes)
+    }
+
+    // Digits in `range`, reading only the blocks it touches
+    pub fn read_range(&mut self, range: Range<usize>) -> Result<String, CacheError> {
+        let range = range.start.min(self.header.total_digits)..range.end.min(self.header.total_digits);
+        let mut digits = String::with_capacity(range.len());
+        if range.is_empty() {
+            return Ok(digits);
+        }
+        for block in range.start / self.header.block_digits..=(range.end - 1) / self.header.block_digits {
+            let block_range = self.header.block_range(block);
+            let text = self.read_block(block)?;
+            let start = range.start.max(block_range.start) - block_range.start;
+            let end = range.end.min(block_range.end) - block_range.start;
+            digits.push_str(&text[start..end]);
+        }
+        Ok(digits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digits(n: usize) -> String {
+        (0..n).map(|i| char::from(b'0' + (i * 7 % 10) as u8)).collect()
+    }
+
+    fn metadata() -> CacheMetadata<'static> {
+        CacheMetadata { integer_digits: 1, constant: "pi", source: "test", created: 0 }
+    }
+
+    #[test]
+    fn checksum() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b"The quick brown fox jumps over the lazy dog"), 0x414f_a339);
+    }
+
+    #[test]
+    fn round_trip() {
+        // digit counts that end in a group of one, two or three digits, and a partial last block
+        for n in [0, 1, 2, 3, 4, 5, 1000, BLOCK_DIGITS, BLOCK_DIGITS + 1, 2 * BLOCK_DIGITS + 2] {
+            let all = digits(n);
+            let (header, decoded) = decode(&encode(&all, &metadata()), true).unwrap();
+            assert_eq!(decoded, all, "{n} digits");
+            assert_eq!(header.total_digits, n);
+            assert_eq!(header.blocks(), n.div_ceil(BLOCK_DIGITS));
+            assert_eq!(header.integer_digits, 1);
+            assert_eq!(header.constant, "pi");
+        }
+        assert_eq!(unpack(&pack("0099"), 4).unwrap(), "0099");
+    }
+
+    #[test]
+    fn truncated() {
+        let all = digits(BLOCK_DIGITS + 100);
+        let bytes = encode(&all, &metadata());
+        // only whole blocks of a partial file are read
+        let (_, decoded) = decode(&bytes[..bytes.len() - 1], false).unwrap();
+        assert_eq!(decoded, all[..BLOCK_DIGITS]);
+        assert!(decode(&bytes[..bytes.len() - 1], true).is_err());
+        assert!(decode(&bytes[..20], true).is_err());
+        assert!(decode(b"PIDIGITZ", true).is_err());
+    }
+
+    #[test]
+    fn corrupt() {
+        let mut bytes = encode(&digits(BLOCK_DIGITS + 100), &metadata());
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        assert!(matches!(decode(&bytes, true), Err(CacheError::Checksum { block: Some(1) })));
+        bytes[last] ^= 1;
+        // the other header slot is used while one is corrupt
+        let slot_len = u32::from_le_bytes(bytes[10..14].try_into().unwrap()) as usize;
+        bytes[PREAMBLE_LEN + 30] ^= 1;
+        assert!(decode(&bytes, true).is_ok());
+        bytes[PREAMBLE_LEN + slot_len + 30] ^= 1;
+        assert!(matches!(decode(&bytes, true), Err(CacheError::Checksum { block: None })));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn reader() {
+        let path = std::env::temp_dir().join(format!("pi-search-reader-{}.{FILE_EXTENSION}", std::process::id()));
+        let all = digits(2 * BLOCK_DIGITS + 2);
+        let mut bytes = encode(&all, &metadata());
+        std::fs::write(&path, &bytes).unwrap();
+
+        let mut reader = CacheReader::open(&path).unwrap();
+        assert_eq!(reader.header().total_digits, all.len());
+        assert_eq!(reader.read_block(2).unwrap(), all[2 * BLOCK_DIGITS..]);
+        assert_eq!(reader.read_range(BLOCK_DIGITS - 5..BLOCK_DIGITS + 5).unwrap(), all[BLOCK_DIGITS - 5..BLOCK_DIGITS + 5]);
+        assert_eq!(reader.read_range(all.len() - 1..all.len() + 10).unwrap(), all[all.len() - 1..]);
+        assert_eq!(reader.read_range(5..5).unwrap(), "");
+        assert!(reader.read_block(3).is_err());
+
+        // a corrupt block fails its checksum, the others still read
+        let i = bytes.len() - 1;
+        bytes[i] ^= 0x10;
+        std::fs::write(&path, &bytes).unwrap();
+        let mut reader = CacheReader::open(&path).unwrap();
+        assert!(matches!(reader.read_block(2), Err(CacheError::Checksum { block: Some(2) })));
+        assert_eq!(reader.read_block(0).unwrap(), all[..BLOCK_DIGITS]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn append() {
+        let path = std::env::temp_dir().join(format!("pi-search-append-{}.{FILE_EXTENSION}", std::process::id()));
+        let all = digits(BLOCK_DIGITS * 5 / 2);
+        let len = write_cache(&path, &all[..BLOCK_DIGITS], &metadata()).unwrap();
+        let saved = SavedFile { path: path.clone(), digits: BLOCK_DIGITS, len };
+
+        // a file that changed since isn't touched
+        assert_eq!(append_cache(&SavedFile { len: len + 1, ..saved.clone() }, &all[BLOCK_DIGITS..]).unwrap(), None);
+
+        let len = append_cache(&saved, &all[BLOCK_DIGITS..]).unwrap().unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(bytes.len() as u64, len);
+        let (header, decoded) = decode(&bytes, true).unwrap();
+        assert_eq!(decoded, all);
+        assert_eq!(header.total_digits, all.len());
+        assert_eq!(header.ranges.len(), 1);
+        assert_eq!(header.ranges[0], 0..all.len());
+        assert_eq!(header.generation, 1);
+
+        // a torn header write leaves the previous header, which still reads the digits it had
+        let mut torn = bytes.clone();
+        let slot_len = u32::from_le_bytes(torn[10..14].try_into().unwrap()) as usize;
+        torn[CacheHeader::slot_offset(1, slot_len) + 20] ^= 0xff;
+        let (header, decoded) = decode(&torn, true).unwrap();
+        assert_eq!(header.generation, 0);
+        assert_eq!(decoded, all[..BLOCK_DIGITS]);
+
+        // the last block isn't whole anymore
+        assert_eq!(append_cache(&SavedFile { path: path.clone(), digits: all.len(), len }, "1").unwrap(), None);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
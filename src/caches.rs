@@ -0,0 +1,136 @@
+use std::{ffi::OsString, fs::{self, File, OpenOptions}, io::{self, Write}, path::{Path, PathBuf}};
+
+// Per-user directory for cache files, e.g. ~/.local/share/pi-search on Linux.
+// Falls back to the working directory if the platform has no such directory.
+pub fn data_dir() -> PathBuf {
+    directories_next::ProjectDirs::from("", "", "pi-search")
+        .map(|dirs| dirs.data_dir().to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+pub struct NamedCache {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+// The cache files the user works with, one of them is in use
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+pub struct CacheList {
+    caches: Vec<NamedCache>,
+    current: usize,
+}
+
+impl Default for CacheList {
+    fn default() -> Self {
+        Self {
+            caches: vec![NamedCache { name: "pi".to_string(), path: data_dir().join("pi.txt") }],
+            current: 0,
+        }
+    }
+}
+
+impl CacheList {
+    pub fn caches(&self) -> &[NamedCache] {
+        &self.caches
+    }
+
+    pub fn current(&self) -> &NamedCache {
+        &self.caches[self.current]
+    }
+
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if index < self.caches.len() {
+            self.current = index;
+        }
+    }
+
+    // New cache in the data directory, named after `name`
+    pub fn add(&mut self, name: &str) -> Result<usize, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Name the cache first".to_string());
+        }
+        if self.caches.iter().any(|cache| cache.name == name) {
+            return Err(format!("There already is a cache named {name}"));
+        }
+        let file_name: String = name.chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect();
+        self.caches.push(NamedCache { name: name.to_string(), path: data_dir().join(format!("{file_name}.txt")) });
+        Ok(self.caches.len() - 1)
+    }
+
+    // Forgets the cache, the file stays on disk
+    pub fn remove(&mut self, index: usize) -> Result<(), String> {
+        if self.caches.len() == 1 {
+            return Err("The last cache can't be removed".to_string());
+        }
+        self.caches.remove(index);
+        if self.current > index || self.current == self.caches.len() {
+            self.current -= 1;
+        }
+        Ok(())
+    }
+
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.caches[self.current].path = path;
+    }
+}
+
+// Creates the directory of a cache file before it's written
+pub fn create_parent_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => fs::create_dir_all(parent),
+        _ => Ok(()),
+    }
+}
+
+// Replaces the file in one step: the new content goes to a temporary file next to it, which is
+// renamed over the old one once it's on disk. A crash leaves either the old or the new file.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut tmp_path = OsString::from(path.as_os_str());
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&tmp_path, path)?;
+
+    // make the rename itself durable, not every platform can open directories
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+// A plain text cache file as it was last read or written: `len` bytes holding `digits` digits
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SavedFile {
+    pub path: PathBuf,
+    pub digits: usize,
+    pub len: u64,
+}
+
+// Appends to a file that is still exactly as recorded, returns false if it changed since
+pub fn append_to(saved: &SavedFile, bytes: &[u8]) -> io::Result<bool> {
+    let mut file = match OpenOptions::new().append(true).open(&saved.path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err),
+    };
+    if file.metadata()?.len() != saved.len {
+        return Ok(false);
+    }
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    Ok(true)
+}
@@ -0,0 +1,287 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use std::{path::PathBuf, time::Duration};
+
+use crate::api::MAX_DIGITS_PER_REQUEST;
+
+pub const DEFAULT_BASE_URL: &str = "https://api.pi.delivery/v1/pi";
+pub const DEFAULT_CONFIG_FILE: &str = "pi-search.cfg";
+pub const MAX_WORKERS: usize = 64;
+
+// HTTP client and job settings.
+// The config file is a list of `key = value` lines, `#` starts a comment, missing keys keep their defaults:
+//
+//   base_url = https://api.pi.delivery/v1/pi
+//   verify_tls = true
+//   ca_bundle = /etc/ssl/corp-ca.pem
+//   proxy = http://proxy.local:3128
+//   connect_timeout = 10
+//   read_timeout = 30
+//   user_agent = pi-search/0.1.0
+//   workers = 10
+//   digits_per_request = 1000
+//   max_in_flight = 10
+//   requests_per_second = 0
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "persistence", serde(default))]
+pub struct SearchConfig {
+    pub base_url: String,
+    pub verify_tls: bool,                  // never disable outside of testing
+    pub ca_bundle: Option<PathBuf>,        // PEM file with additional root certificates
+    pub proxy: Option<String>,             // used for all schemes
+    pub connect_timeout: Option<Duration>,
+    pub read_timeout: Option<Duration>,    // covers the whole request, from connecting until the body is read
+    pub user_agent: String,
+    pub workers: usize,                    // threads of a preload or file search, 1..=MAX_WORKERS
+    pub digits_per_request: usize,         // 1..=MAX_DIGITS_PER_REQUEST
+    pub max_in_flight: usize,              // requests at the same time, over all jobs of a client
+    pub requests_per_second: Option<f64>,  // None is unlimited
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            verify_tls: true,
+            ca_bundle: None,
+            proxy: None,
+            connect_timeout: Some(Duration::from_secs(10)),
+            read_timeout: Some(Duration::from_secs(30)),
+            user_agent: format!("pi-search/{}", env!("CARGO_PKG_VERSION")),
+            workers: 10,
+            digits_per_request: MAX_DIGITS_PER_REQUEST,
+            max_in_flight: 10,
+            requests_per_second: None,
+        }
+    }
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "true" | "yes" | "1" => Ok(true),
+        "false" | "no" | "0" => Ok(false),
+        _ => Err(format!("expected true or false, got \"{value}\"")),
+    }
+}
+
+// seconds, 0 or empty disables the timeout
+fn parse_timeout(value: &str) -> Result<Option<Duration>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    let secs: f64 = value.parse().map_err(|_| format!("expected a number of seconds, got \"{value}\""))?;
+    if secs == 0f64 {
+        return Ok(None);
+    }
+    // rejects negative numbers, NaN, infinity and anything too long for a Duration
+    Duration::try_from_secs_f64(secs).map(Some).map_err(|_| format!("timeout must be a non-negative number of seconds, got \"{value}\""))
+}
+
+fn parse_count(value: &str) -> Result<usize, String> {
+    value.parse().map_err(|_| format!("expected a whole number, got \"{value}\""))
+}
+
+// 0 or empty is unlimited
+fn parse_rate(value: &str) -> Result<Option<f64>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    let rate: f64 = value.parse().map_err(|_| format!("expected a number of requests per second, got \"{value}\""))?;
+    if !rate.is_finite() || rate < 0f64 {
+        return Err(format!("requests_per_second must be a non-negative number, got \"{value}\""));
+    }
+    if rate > 0f64 && Duration::try_from_secs_f64(1f64 / rate).is_err() {
+        return Err(format!("requests_per_second is too small, got \"{value}\""));
+    }
+    Ok(if rate == 0f64 { None } else { Some(rate) })
+}
+
+fn optional(value: &str) -> Option<String> {
+    if value.is_empty() { None } else { Some(value.to_string()) }
+}
+
+impl SearchConfig {
+    pub fn parse(text: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut config = Self::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| format!("line {}: expected \"key = value\"", i + 1))?;
+            config.set(key.trim(), value.trim()).map_err(|err| format!("line {}: {err}", i + 1))?;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "base_url" => self.base_url = value.trim_end_matches('/').to_string(),
+            "verify_tls" => self.verify_tls = parse_bool(value)?,
+            "ca_bundle" => self.ca_bundle = optional(value).map(PathBuf::from),
+            "proxy" => self.proxy = optional(value),
+            "connect_timeout" => self.connect_timeout = parse_timeout(value)?,
+            "read_timeout" => self.read_timeout = parse_timeout(value)?,
+            "user_agent" => self.user_agent = value.to_string(),
+            "workers" => self.workers = parse_count(value)?,
+            "digits_per_request" => self.digits_per_request = parse_count(value)?,
+            "max_in_flight" => self.max_in_flight = parse_count(value)?,
+            "requests_per_second" => self.requests_per_second = parse_rate(value)?,
+            _ => return Err(format!("unknown key \"{key}\"")),
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.base_url.starts_with("https://") || self.base_url.starts_with("http://")) {
+            return Err(format!("base_url must be an http(s) URL, got \"{}\"", self.base_url));
+        }
+        if let Some(proxy) = &self.proxy {
+            if !proxy.contains("://") {
+                return Err(format!("proxy must be a URL like http://host:port, got \"{proxy}\""));
+            }
+        }
+        if !(1..=MAX_WORKERS).contains(&self.workers) {
+            return Err(format!("workers must be between 1 and {MAX_WORKERS}, got {}", self.workers));
+        }
+        if !(1..=MAX_DIGITS_PER_REQUEST).contains(&self.digits_per_request) {
+            return Err(format!("digits_per_request must be between 1 and {MAX_DIGITS_PER_REQUEST}, got {}", self.digits_per_request));
+        }
+        if self.max_in_flight == 0 {
+            return Err("max_in_flight must be at least 1".to_string());
+        }
+        if self.requests_per_second.is_some_and(|rate| !rate.is_finite() || rate <= 0f64) {
+            return Err(format!("requests_per_second must be positive, got {}", self.requests_per_second.unwrap()));
+        }
+        if self.requests_per_second.is_some_and(|rate| Duration::try_from_secs_f64(1f64 / rate).is_err()) {
+            return Err(format!("requests_per_second is too small, got {}", self.requests_per_second.unwrap()));
+        }
+        Ok(())
+    }
+
+    pub fn to_config_string(&self) -> String {
+        let timeout = |t: Option<Duration>| t.map_or("0".to_string(), |t| t.as_secs_f64().to_string());
+        let mut text = String::default();
+        text.push_str(format!("base_url = {}\n", self.base_url).as_str());
+        text.push_str(format!("verify_tls = {}\n", self.verify_tls).as_str());
+        text.push_str(format!("ca_bundle = {}\n", self.ca_bundle.as_ref().map_or(String::default(), |p| p.display().to_string())).as_str());
+        text.push_str(format!("proxy = {}\n", self.proxy.as_deref().unwrap_or_default()).as_str());
+        text.push_str(format!("connect_timeout = {}\n", timeout(self.connect_timeout)).as_str());
+        text.push_str(format!("read_timeout = {}\n", timeout(self.read_timeout)).as_str());
+        text.push_str(format!("user_agent = {}\n", self.user_agent).as_str());
+        text.push_str(format!("workers = {}\n", self.workers).as_str());
+        text.push_str(format!("digits_per_request = {}\n", self.digits_per_request).as_str());
+        text.push_str(format!("max_in_flight = {}\n", self.max_in_flight).as_str());
+        text.push_str(format!("requests_per_second = {}\n", self.requests_per_second.unwrap_or(0f64)).as_str());
+        text
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::parse(std::fs::read_to_string(path)?.as_str())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        crate::caches::create_parent_dir(path)?;
+        Ok(std::fs::write(path, self.to_config_string())?)
+    }
+
+    // The config file next to the caches in the per-user data directory
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn default_path() -> PathBuf {
+        crate::caches::data_dir().join(DEFAULT_CONFIG_FILE)
+    }
+
+    // Uses the default config file if there is one
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_or_default() -> Self {
+        let path = Self::default_path();
+        if !path.exists() {
+            return Self::default();
+        }
+        Self::load(&path).unwrap_or_else(|err| {
+            eprintln!("Error while loading {}: {err}", path.display());
+            Self::default()
+        })
+    }
+
+    pub fn digits_url(&self, start: usize, number_of_digits: usize) -> String {
+        format!("{}?start={start}&numberOfDigits={number_of_digits}", self.base_url)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn build_blocking_client(&self) -> Result<reqwest::blocking::Client, Box<dyn std::error::Error>> {
+        let mut builder = reqwest::blocking::Client::builder()
+            .danger_accept_invalid_certs(!self.verify_tls)
+            .user_agent(self.user_agent.as_str())
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.read_timeout);
+        if let Some(ca_bundle) = &self.ca_bundle {
+            builder = builder.add_root_certificate(reqwest::Certificate::from_pem(&std::fs::read(ca_bundle)?)?);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy.as_str())?);
+        }
+        Ok(builder.build()?)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn build_client(&self) -> Result<reqwest::Client, Box<dyn std::error::Error>> {
+        let mut builder = reqwest::Client::builder()
+            .danger_accept_invalid_certs(!self.verify_tls)
+            .user_agent(self.user_agent.as_str());
+        if let Some(connect_timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(connect_timeout);
+        }
+        if let Some(read_timeout) = self.read_timeout {
+            builder = builder.timeout(read_timeout);
+        }
+        if let Some(ca_bundle) = &self.ca_bundle {
+            builder = builder.add_root_certificate(reqwest::Certificate::from_pem(&std::fs::read(ca_bundle)?)?);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy.as_str())?);
+        }
+        Ok(builder.build()?)
+    }
+
+    // The browser owns TLS, proxies, timeouts and the user agent, only base_url applies
+    #[cfg(target_arch = "wasm32")]
+    pub fn build_client(&self) -> Result<reqwest::Client, Box<dyn std::error::Error>> {
+        Ok(reqwest::Client::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeouts() {
+        assert_eq!(parse_timeout(""), Ok(None));
+        assert_eq!(parse_timeout("0"), Ok(None));
+        assert_eq!(parse_timeout("1.5"), Ok(Some(Duration::from_millis(1500))));
+        assert!(parse_timeout("-1").is_err());
+        assert!(parse_timeout("NaN").is_err());
+        assert!(parse_timeout("inf").is_err());
+        assert!(parse_timeout("1e30").is_err());
+        assert!(parse_timeout("soon").is_err());
+    }
+
+    #[test]
+    fn rates() {
+        assert_eq!(parse_rate(""), Ok(None));
+        assert_eq!(parse_rate("0"), Ok(None));
+        assert_eq!(parse_rate("2.5"), Ok(Some(2.5)));
+        assert!(parse_rate("-1").is_err());
+        assert!(parse_rate("inf").is_err());
+        assert!(parse_rate("1e-300").is_err());
+        // set without parsing, as a persisted config is
+        let config = SearchConfig { requests_per_second: Some(1e-300), ..Default::default() };
+        assert!(config.validate().is_err());
+        assert!(SearchConfig { requests_per_second: Some(2.5), ..config }.validate().is_ok());
+    }
+}
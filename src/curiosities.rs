@@ -0,0 +1,256 @@
+use crate::{analysis::*, position::PositionConvention};
+
+// Which curiosities to look for. Positions are counted the way `convention` counts them, a string
+// that locates itself in one convention doesn't in the others.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+pub struct CuriosityOptions {
+    pub convention: PositionConvention,
+    pub self_locating: bool,
+    pub digit_is_index_run: Option<usize>, // shortest run of digits equal to their position mod 10
+    pub repeated_run: Option<usize>,       // shortest run of one digit
+}
+
+impl Default for CuriosityOptions {
+    fn default() -> Self {
+        Self {
+            convention: PositionConvention::default(),
+            self_locating: true,
+            digit_is_index_run: Some(3),
+            repeated_run: Some(6),
+        }
+    }
+}
+
+impl CuriosityOptions {
+    pub fn analyzers(&self, integer_digits: usize) -> Vec<Box<dyn Analyzer>> {
+        let mut analyzers: Vec<Box<dyn Analyzer>> = Vec::new();
+        if self.self_locating {
+            analyzers.push(Box::new(SelfLocating::new(self.convention, integer_digits)));
+        }
+        if let Some(min_run) = self.digit_is_index_run {
+            analyzers.push(Box::new(DigitIsIndex::new(self.convention, integer_digits, min_run)));
+        }
+        if let Some(min_run) = self.repeated_run {
+            analyzers.push(Box::new(RepeatedDigits::new(min_run)));
+        }
+        analyzers
+    }
+}
+
+// Strings of digits of n that start at position n, like 16470 at position 16470
+pub struct SelfLocating {
+    convention: PositionConvention,
+    integer_digits: usize,
+    buffer: String, // digits that may still start one, a number can run into the next chunk
+    start: usize,   // offset of the buffer
+}
+
+impl SelfLocating {
+    pub fn new(convention: PositionConvention, integer_digits: usize) -> Self {
+        Self { convention, integer_digits, buffer: String::new(), start: 0usize }
+    }
+}
+
+impl Analyzer for SelfLocating {
+    fn feed(&mut self, chunk: &str, start: usize, findings: &mut Vec<Finding>) {
+        if self.buffer.is_empty() {
+            self.start = start;
+        }
+        self.buffer.push_str(chunk);
+
+        let bytes = self.buffer.as_bytes();
+        let mut checked = 0usize;
+        while checked < bytes.len() {
+            let offset = self.start + checked;
+            let position = self.convention.to_position(offset, self.integer_digits);
+            if position < 0 {
+                checked += 1;
+                continue;
+            }
+            let width = position.checked_ilog10().map_or(1usize, |log| log as usize + 1);
+            if checked + width > bytes.len() {
+                break;
+            }
+            // most positions fail on the first digit, no need to format them
+            let leading = (position / 10i64.pow(width as u32 - 1)) as u8;
+            if bytes[checked] - b'0' == leading && self.buffer[checked..checked + width] == position.to_string() {
+                findings.push(Finding {
+                    label: "self-locating".to_string(),
+                    position: offset,
+                    digits: position.to_string(),
+                });
+            }
+            checked += 1;
+        }
+        self.buffer.drain(..checked);
+        self.start += checked;
+    }
+}
+
+// Runs of digits that each equal their position mod 10, like the 7, 8, 9 at positions 7, 8, 9
+pub struct DigitIsIndex {
+    convention: PositionConvention,
+    integer_digits: usize,
+    min_run: usize,
+    run: Option<(usize, String)>, // offset and digits of the current run
+}
+
+impl DigitIsIndex {
+    pub fn new(convention: PositionConvention, integer_digits: usize, min_run: usize) -> Self {
+        Self { convention, integer_digits, min_run: min_run.max(1), run: None }
+    }
+
+    fn end_run(&mut self, findings: &mut Vec<Finding>) {
+        if let Some((position, digits)) = self.run.take() {
+            if digits.len() >= self.min_run {
+                findings.push(Finding { label: "digit = position mod 10".to_string(), position, digits });
+            }
+        }
+    }
+}
+
+impl Analyzer for DigitIsIndex {
+    fn feed(&mut self, chunk: &str, start: usize, findings: &mut Vec<Finding>) {
+        for (i, digit) in chunk.bytes().enumerate() {
+            let offset = start + i;
+            let position = self.convention.to_position(offset, self.integer_digits);
+            if i64::from(digit - b'0') == position.rem_euclid(10) {
+                self.run.get_or_insert_with(|| (offset, String::new())).1.push(digit as char);
+            }
+            else {
+                self.end_run(findings);
+            }
+        }
+    }
+
+    fn finish(&mut self, _end: usize, findings: &mut Vec<Finding>) {
+        self.end_run(findings);
+    }
+}
+
+// Runs of one repeated digit, like the six 9s of the Feynman point
+pub struct RepeatedDigits {
+    min_run: usize,
+    run: Option<(u8, usize, usize)>, // digit, offset, length
+    feynman_point: Option<bool>,     // whether the next six 9s are the first ones, known once the scan starts
+}
+
+impl RepeatedDigits {
+    pub fn new(min_run: usize) -> Self {
+        Self { min_run: min_run.max(2), run: None, feynman_point: None }
+    }
+
+    fn end_run(&mut self, findings: &mut Vec<Finding>) {
+        if let Some((digit, position, len)) = self.run.take() {
+            if len < self.min_run {
+                return;
+            }
+            let label = if digit == b'9' && len >= 6 && self.feynman_point == Some(true) {
+                self.feynman_point = Some(false);
+                "Feynman point".to_string()
+            }
+            else {
+                format!("{len} × {}", digit as char)
+            };
+            findings.push(Finding { label, position, digits: (digit as char).to_string().repeat(len) });
+        }
+    }
+}
+
+impl Analyzer for RepeatedDigits {
+    fn feed(&mut self, chunk: &str, start: usize, findings: &mut Vec<Finding>) {
+        self.feynman_point.get_or_insert(start == 0);
+        for (i, digit) in chunk.bytes().enumerate() {
+            match &mut self.run {
+                Some((d, _, len)) if *d == digit => *len += 1,
+                _ => {
+                    self.end_run(findings);
+                    self.run = Some((digit, start + i, 1usize));
+                },
+            }
+        }
+    }
+
+    fn finish(&mut self, _end: usize, findings: &mut Vec<Finding>) {
+        self.end_run(findings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::analysis::tests::{positions, run};
+
+    #[test]
+    fn self_locating() {
+        let mut analyzer = SelfLocating::new(PositionConvention::CacheOffset, 1);
+        assert_eq!(positions(&run(&mut analyzer, &["333433333333", "12"], 0)), [(12, "12")]);
+        // a number running into the next chunk
+        let mut analyzer = SelfLocating::new(PositionConvention::CacheOffset, 1);
+        assert_eq!(positions(&run(&mut analyzer, &["3334333333331", "2"], 0)), [(12, "12")]);
+        // one that the stream ends in the middle of
+        let mut analyzer = SelfLocating::new(PositionConvention::CacheOffset, 1);
+        assert!(run(&mut analyzer, &["3334333333331"], 0).is_empty());
+
+        let mut analyzer = SelfLocating::new(PositionConvention::CacheOffset, 1);
+        assert_eq!(positions(&run(&mut analyzer, &["0"], 0)), [(0, "0")]);
+        // the same digits locate themselves in one convention only
+        let mut analyzer = SelfLocating::new(PositionConvention::AfterPointOneBased, 1);
+        assert_eq!(positions(&run(&mut analyzer, &["31"], 0)), [(1, "1")]);
+        let mut analyzer = SelfLocating::new(PositionConvention::AfterPointZeroBased, 1);
+        assert!(run(&mut analyzer, &["31"], 0).is_empty());
+
+        let mut analyzer = SelfLocating::new(PositionConvention::CacheOffset, 1);
+        assert!(run(&mut analyzer, &[""], 0).is_empty());
+    }
+
+    #[test]
+    fn digit_is_index() {
+        let mut analyzer = DigitIsIndex::new(PositionConvention::CacheOffset, 1, 3);
+        assert_eq!(positions(&run(&mut analyzer, &["0129", "1"], 0)), [(0, "012")]);
+
+        // a run at the end of the stream is only reported when it finishes
+        let mut analyzer = DigitIsIndex::new(PositionConvention::CacheOffset, 1, 3);
+        let mut findings = Vec::new();
+        analyzer.feed("99234", 0, &mut findings);
+        assert!(findings.is_empty());
+        analyzer.finish(5, &mut findings);
+        assert_eq!(positions(&findings), [(2, "234")]);
+
+        // positions wrap around mod 10, runs continue across chunks
+        let mut analyzer = DigitIsIndex::new(PositionConvention::CacheOffset, 1, 3);
+        assert_eq!(positions(&run(&mut analyzer, &["89", "01"], 8)), [(8, "8901")]);
+
+        let mut analyzer = DigitIsIndex::new(PositionConvention::CacheOffset, 1, 3);
+        assert!(run(&mut analyzer, &["01", "5"], 0).is_empty());
+    }
+
+    #[test]
+    fn repeated() {
+        let mut analyzer = RepeatedDigits::new(6);
+        let findings = run(&mut analyzer, &["1999", "9992", "999999"], 0);
+        assert_eq!(positions(&findings), [(1, "999999"), (8, "999999")]);
+        assert_eq!(findings[0].label, "Feynman point");
+        assert_eq!(findings[1].label, "6 × 9");
+
+        // a scan that doesn't start at the beginning can't know which run is the first
+        let mut analyzer = RepeatedDigits::new(6);
+        let findings = run(&mut analyzer, &["999999"], 100);
+        assert_eq!(positions(&findings), [(100, "999999")]);
+        assert_eq!(findings[0].label, "6 × 9");
+
+        let mut analyzer = RepeatedDigits::new(3);
+        assert_eq!(positions(&run(&mut analyzer, &["00012", "2"], 0)), [(0, "000")]);
+        let mut analyzer = RepeatedDigits::new(3);
+        assert!(run(&mut analyzer, &[""], 0).is_empty());
+    }
+
+    #[test]
+    fn options() {
+        assert_eq!(CuriosityOptions::default().analyzers(1).len(), 3);
+        let options = CuriosityOptions { self_locating: false, digit_is_index_run: None, ..CuriosityOptions::default() };
+        assert_eq!(options.analyzers(1).len(), 1);
+    }
+}
@@ -0,0 +1,177 @@
+use std::time::Duration;
+
+use crate::job::SearchBounds;
+
+// How long a search should take, assuming the digits behave like a random digit stream (which
+// nobody has proven for pi, but every test so far agrees with).
+//
+// The expected number of digits read until a pattern first completes is the sum of 10^k over the
+// lengths k at which the pattern overlaps itself, the whole pattern included: 10^4 for "1234", but
+// 10^4 + 10^3 + 10^2 + 10 for "1111". Self-overlapping patterns take longer because their matches
+// come in clumps. The first match position is then roughly geometric.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PatternEstimate {
+    pub pattern_len: usize,
+    pub expected_wait: f64, // digits read until the first match is complete
+}
+
+impl PatternEstimate {
+    pub fn new(pattern: &str) -> Self {
+        let bytes = pattern.as_bytes();
+        let expected_wait = (1..=bytes.len())
+            .filter(|&k| bytes[..k] == bytes[bytes.len() - k..])
+            .map(|k| 10f64.powi(k as i32))
+            .sum();
+        Self { pattern_len: bytes.len(), expected_wait }
+    }
+
+    // Expected offset of the first match from where the search starts
+    pub fn expected_position(&self) -> f64 {
+        (self.expected_wait - self.pattern_len as f64).max(0f64)
+    }
+
+    // Chance for a match to start at any one position, as if matches didn't clump
+    fn rate(&self) -> f64 {
+        1f64 / (self.expected_position() + 1f64)
+    }
+
+    // Probability that a match starts among the first `positions` positions
+    pub fn probability_within(&self, positions: usize) -> f64 {
+        let rate = self.rate();
+        if rate >= 1f64 {
+            return if positions > 0 { 1f64 } else { 0f64 };
+        }
+        -(positions as f64 * (-rate).ln_1p()).exp_m1()
+    }
+
+    // Share of random digit streams in which the first match comes at `offset` or earlier:
+    // close to 0 is lucky, close to 1 unlucky
+    pub fn luck(&self, offset: usize) -> f64 {
+        self.probability_within(offset + 1)
+    }
+
+    // The wait doesn't get shorter as positions are ruled out, a geometric distribution has no memory
+    pub fn expected_position_after(&self, searched: usize) -> f64 {
+        searched as f64 + self.expected_position()
+    }
+}
+
+pub fn luck_name(luck: f64) -> &'static str {
+    if luck < 0.05 {
+        "very lucky"
+    }
+    else if luck < 0.25 {
+        "lucky"
+    }
+    else if luck <= 0.75 {
+        "typical"
+    }
+    else if luck <= 0.95 {
+        "unlucky"
+    }
+    else {
+        "very unlucky"
+    }
+}
+
+// Estimate for a search over `bounds`, with `cached` digits in the cache
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchEstimate {
+    pub pattern: PatternEstimate,
+    pub expected_position: f64,  // absolute
+    pub in_cache: f64,           // probability of a match starting among the cached digits
+    pub in_bounds: Option<f64>,  // probability of a match before the limit of the bounds
+    pub eta: Option<Duration>,   // until the expected position at `rate` digits/s, None without a rate or past what a Duration holds
+}
+
+pub fn estimate_search(pattern: &str, bounds: &SearchBounds, cached: usize, rate: Option<f64>) -> SearchEstimate {
+    let estimate = PatternEstimate::new(pattern);
+    let expected_position = bounds.start as f64 + estimate.expected_position();
+    // a match in the cache has to end there too
+    let cached_positions = (cached + 1).saturating_sub(estimate.pattern_len.max(1)).saturating_sub(bounds.start);
+    let in_cache = estimate.probability_within(cached_positions);
+    let in_bounds = bounds.limit().map(|limit| estimate.probability_within(limit.saturating_sub(bounds.start)));
+    let eta = rate.filter(|&rate| rate > 0f64).and_then(|rate| {
+        let to_download = (expected_position + estimate.pattern_len as f64 - cached as f64).max(0f64);
+        Duration::try_from_secs_f64(to_download / rate).ok()
+    });
+    SearchEstimate { pattern: estimate, expected_position, in_cache, in_bounds, eta }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlaps() {
+        assert_eq!(PatternEstimate::new("1234").expected_wait, 1e4);
+        assert_eq!(PatternEstimate::new("1111").expected_wait, 1e4 + 1e3 + 1e2 + 1e1);
+        assert_eq!(PatternEstimate::new("1211").expected_wait, 1e4 + 1e1);
+        assert_eq!(PatternEstimate::new("").expected_wait, 0f64);
+    }
+
+    #[test]
+    fn geometric() {
+        let estimate = PatternEstimate::new("7");
+        assert_eq!(estimate.expected_position(), 9f64);
+        assert_eq!(estimate.probability_within(0), 0f64);
+        assert!((estimate.probability_within(1) - 0.1).abs() < 1e-12);
+        assert!((estimate.luck(1) - 0.19).abs() < 1e-12);
+    }
+
+    #[test]
+    fn empty() {
+        let estimate = PatternEstimate::new("");
+        assert_eq!(estimate.expected_position(), 0f64);
+        assert_eq!(estimate.probability_within(0), 0f64);
+        assert_eq!(estimate.probability_within(1), 1f64);
+    }
+
+    #[test]
+    fn long() {
+        let estimate = PatternEstimate::new(&"12".repeat(200));
+        assert!(estimate.expected_position().is_infinite());
+        assert_eq!(estimate.probability_within(usize::MAX), 0f64);
+
+        let estimate = PatternEstimate::new(&"1234567890".repeat(3));
+        assert!(estimate.expected_position().is_finite());
+        assert!(estimate.probability_within(1_000_000) < 1e-20);
+    }
+
+    #[test]
+    fn rates() {
+        let bounds = SearchBounds::default();
+        assert_eq!(estimate_search("123", &bounds, 100, None).eta, None);
+        assert_eq!(estimate_search("123", &bounds, 100, Some(0f64)).eta, None);
+        assert_eq!(estimate_search("123", &bounds, 100, Some(-1f64)).eta, None);
+    }
+
+    #[test]
+    fn eta() {
+        let bounds = SearchBounds::default();
+        let pattern = "1234567890".repeat(3);
+        assert_eq!(estimate_search(&pattern, &bounds, 1_000, Some(1e3)).eta, None);
+        assert_eq!(estimate_search(&"12".repeat(200), &bounds, 1_000, Some(1e3)).eta, None);
+    }
+
+    #[test]
+    fn chances() {
+        let estimate = estimate_search("5", &SearchBounds::from(10), 20, Some(100f64));
+        assert!((estimate.in_cache - (1f64 - 0.9f64.powi(10))).abs() < 1e-12);
+        assert_eq!(estimate.in_bounds, None);
+        assert_eq!(estimate.expected_position, 19f64);
+        assert_eq!(estimate.eta, Some(Duration::ZERO));
+
+        let bounds = SearchBounds { start: 0, end: Some(5), ..SearchBounds::default() };
+        let estimate = estimate_search("5", &bounds, 0, Some(1f64));
+        assert!((estimate.in_bounds.unwrap() - (1f64 - 0.9f64.powi(5))).abs() < 1e-12);
+        assert_eq!(estimate.in_cache, 0f64);
+        assert_eq!(estimate.eta, Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn uncached() {
+        let estimate = estimate_search("123456", &SearchBounds::default(), 3, None);
+        assert_eq!(estimate.in_cache, 0f64);
+    }
+}
@@ -0,0 +1,142 @@
+use eframe::egui::{self, Ui};
+use std::{fs, path::PathBuf};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DialogMode {
+    Open,
+    Save,
+}
+
+pub enum DialogResult {
+    Picked(PathBuf),
+    Cancelled,
+}
+
+// Minimal file browser window for picking a file to read or a path to write
+pub struct FileDialog {
+    mode: DialogMode,
+    dir: PathBuf,
+    file_name: String,
+    entries: Vec<(String, bool)>, // name, is a directory
+    error: Option<String>,
+}
+
+impl FileDialog {
+    pub fn new(mode: DialogMode, current: &std::path::Path) -> Self {
+        let dir = match current.parent() {
+            Some(parent) if parent.is_dir() => parent.to_path_buf(),
+            _ => std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+        };
+        let file_name = current.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        let mut dialog = Self {
+            mode,
+            dir,
+            file_name,
+            entries: Vec::new(),
+            error: None,
+        };
+        dialog.refresh();
+        dialog
+    }
+
+    pub fn mode(&self) -> DialogMode {
+        self.mode
+    }
+
+    fn refresh(&mut self) {
+        self.entries.clear();
+        match fs::read_dir(&self.dir) {
+            Ok(entries) => {
+                self.error = None;
+                for entry in entries.flatten() {
+                    let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+                    self.entries.push((entry.file_name().to_string_lossy().into_owned(), is_dir));
+                }
+                // directories first
+                self.entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.to_lowercase().cmp(&b.0.to_lowercase())));
+            },
+            Err(err) => self.error = Some(format!("Can't read {}: {err}", self.dir.display())),
+        }
+    }
+
+    fn enter(&mut self, dir: PathBuf) {
+        self.dir = dir;
+        self.refresh();
+    }
+
+    pub fn show(&mut self, ctx: &egui::CtxRef) -> Option<DialogResult> {
+        let title = match self.mode {
+            DialogMode::Open => "Open cache file",
+            DialogMode::Save => "Save cache file as",
+        };
+        let mut result = None;
+        let mut open = true;
+        egui::Window::new(title).open(&mut open).collapsible(false).default_width(400f32).show(ctx, |ui| {
+            result = self.contents(ui);
+        });
+        if !open {
+            result = Some(DialogResult::Cancelled);
+        }
+        result
+    }
+
+    fn contents(&mut self, ui: &mut Ui) -> Option<DialogResult> {
+        ui.horizontal(|ui| {
+            if ui.button("Up").clicked() {
+                if let Some(parent) = self.dir.parent() {
+                    let parent = parent.to_path_buf();
+                    self.enter(parent);
+                }
+            }
+            ui.label(self.dir.display().to_string());
+        });
+
+        let mut enter = None;
+        egui::ScrollArea::vertical().max_height(300f32).show(ui, |ui| {
+            for (name, is_dir) in &self.entries {
+                let label = if *is_dir { format!("{name}/") } else { name.clone() };
+                let response = ui.selectable_label(!is_dir && *name == self.file_name, label);
+                if response.clicked() {
+                    if *is_dir {
+                        enter = Some(self.dir.join(name));
+                    }
+                    else {
+                        self.file_name = name.clone();
+                    }
+                }
+            }
+        });
+        if let Some(dir) = enter {
+            self.enter(dir);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("File name: ");
+            ui.text_edit_singleline(&mut self.file_name);
+        });
+        if let Some(err) = &self.error {
+            ui.colored_label(egui::Color32::RED, err);
+        }
+
+        let mut result = None;
+        ui.horizontal(|ui| {
+            let confirm = match self.mode {
+                DialogMode::Open => "Open",
+                DialogMode::Save => "Save",
+            };
+            if ui.add_enabled(!self.file_name.is_empty(), egui::Button::new(confirm)).clicked() {
+                let path = self.dir.join(&self.file_name);
+                if self.mode == DialogMode::Open && !path.is_file() {
+                    self.error = Some(format!("{} is not a file", path.display()));
+                }
+                else {
+                    result = Some(DialogResult::Picked(path));
+                }
+            }
+            if ui.button("Cancel").clicked() {
+                result = Some(DialogResult::Cancelled);
+            }
+        });
+        result
+    }
+}
@@ -0,0 +1,211 @@
+use std::{fs::File, io::{Read, Seek, SeekFrom}, ops::Range, path::{Path, PathBuf}, sync::{atomic::{AtomicUsize, Ordering}, mpsc::{self, Receiver}, Arc}, thread};
+
+use crate::{cache_format::{is_binary_cache, CacheHeader, CacheReader}, job::*};
+
+pub const DEFAULT_CHUNK_DIGITS: usize = 1 << 20;
+
+// A cache file that can be read at any digit offset without loading it: our plain text
+// format ("3.1415...", see position::format_cache) or a binary cache. Other formats have to be
+// read into memory first, the importer knows how to clean them up.
+#[derive(Clone, Debug)]
+pub enum DigitFile {
+    Text { path: PathBuf, point: Option<usize>, digits: usize }, // the decimal point follows `point` digits
+    Binary { path: PathBuf, header: CacheHeader },
+}
+
+impl DigitFile {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let mut file = File::open(path).map_err(|err| format!("Can't open {}: {err}", path.display()))?;
+        let len = file.metadata().map_err(|err| err.to_string())?.len() as usize;
+        let mut start = Vec::new();
+        (&mut file).take(64).read_to_end(&mut start).map_err(|err| err.to_string())?;
+
+        if is_binary_cache(&start) {
+            let reader = CacheReader::open(path).map_err(|err| err.to_string())?;
+            return Ok(DigitFile::Binary { path: path.to_path_buf(), header: reader.header().clone() });
+        }
+
+        // a line break at the end isn't a digit
+        let mut end = [0u8; 2];
+        let mut trailing = 0usize;
+        if len >= 2 {
+            file.seek(SeekFrom::Start(len as u64 - 2)).map_err(|err| err.to_string())?;
+            file.read_exact(&mut end).map_err(|err| err.to_string())?;
+            trailing = end.iter().rev().take_while(|&&b| b == b'\n' || b == b'\r').count();
+        }
+
+        let point = start.iter().position(|&b| b == b'.');
+        let digits = len - trailing - usize::from(point.is_some());
+        let file = DigitFile::Text { path: path.to_path_buf(), point, digits };
+        // catches formatted files early, every read checks its digits too
+        file.reader()?.read(0..digits.min(64))?;
+        Ok(file)
+    }
+
+    pub fn digits(&self) -> usize {
+        match self {
+            DigitFile::Text { digits, .. } => *digits,
+            DigitFile::Binary { header, .. } => header.total_digits,
+        }
+    }
+
+    pub fn integer_digits(&self) -> Option<usize> {
+        match self {
+            DigitFile::Text { point, .. } => *point,
+            DigitFile::Binary { header, .. } => Some(header.integer_digits),
+        }
+    }
+
+    // Each worker reads through its own handle
+    fn reader(&self) -> Result<SegmentReader, String> {
+        match self {
+            DigitFile::Text { path, point, .. } => {
+                let file = File::open(path).map_err(|err| format!("Can't open {}: {err}", path.display()))?;
+                Ok(SegmentReader::Text(file, *point))
+            },
+            DigitFile::Binary { path, .. } => Ok(SegmentReader::Binary(CacheReader::open(path).map_err(|err| err.to_string())?)),
+        }
+    }
+}
+
+enum SegmentReader {
+    Text(File, Option<usize>),
+    Binary(CacheReader),
+}
+
+impl SegmentReader {
+    fn read(&mut self, range: Range<usize>) -> Result<String, String> {
+        match self {
+            SegmentReader::Text(file, point) => {
+                // digits after the decimal point are one byte further into the file
+                let point = *point;
+                let byte = |offset: usize| if point.is_some_and(|p| offset >= p) { offset + 1 } else { offset };
+                let (start, end) = (byte(range.start), if range.is_empty() { byte(range.start) } else { byte(range.end - 1) + 1 });
+
+                let mut bytes = vec![0u8; end - start];
+                file.seek(SeekFrom::Start(start as u64)).map_err(|err| err.to_string())?;
+                file.read_exact(&mut bytes).map_err(|err| err.to_string())?;
+                if let Some(p) = point.filter(|&p| start <= p && p < end) {
+                    bytes.remove(p - start);
+                }
+                if let Some(i) = bytes.iter().position(|b| !b.is_ascii_digit()) {
+                    return Err(format!("Unexpected {:?} at digit {}, only plain digit files can be searched on disk", bytes[i] as char, range.start + i));
+                }
+                Ok(String::from_utf8(bytes).unwrap())
+            },
+            SegmentReader::Binary(reader) => reader.read_range(range).map_err(|err| err.to_string()),
+        }
+    }
+}
+
+enum WorkerMessage {
+    Scanned(usize),
+    Failed(String),
+}
+
+// Finds the first occurrence of the pattern starting inside `range` (the whole file if None).
+// The range is split into one segment per thread; a match may run up to len - 1 digits past its
+// segment, so each segment reads that far into the next one, like the network search keeps the
+// previous response around.
+pub fn search_file(file: Arc<DigitFile>, pattern: &str, range: Option<Range<usize>>, threads: usize, chunk_digits: usize) -> Receiver<JobEvent> {
+    let (ev_tx, ev_rx) = mpsc::channel();
+    let pattern = pattern.to_string();
+
+    thread::spawn(move || {
+        let digits = file.digits();
+        let reason = match &range {
+            Some(range) if range.end <= digits => StopReason::EndOfRange,
+            _ => StopReason::EndOfFile,
+        };
+        let range = range.map_or(0..digits, |range| range.start.min(digits)..range.end.min(digits));
+        let total = range.len();
+        if ev_tx.send(JobEvent::Started { total: Some(total) }).is_err() {
+            eprintln!("Main thread is dead");
+            return;
+        }
+        if pattern.is_empty() {
+            let _ = ev_tx.send(JobEvent::Error("Nothing to search for".to_string()));
+            return;
+        }
+
+        let best = Arc::new(AtomicUsize::new(usize::MAX));
+        let (worker_tx, worker_rx) = mpsc::channel();
+        let threads = threads.clamp(1, total.div_ceil(chunk_digits).max(1));
+        let segment_len = total.div_ceil(threads);
+        for i in 0..threads {
+            let segment = (range.start + i * segment_len)..(range.start + (i + 1) * segment_len).min(range.end);
+            let scan_end = (segment.end + pattern.len() - 1).min(digits);
+            let (file, pattern, best, worker_tx) = (file.clone(), pattern.clone(), best.clone(), worker_tx.clone());
+
+            thread::spawn(move || {
+                let mut reader = match file.reader() {
+                    Ok(reader) => reader,
+                    Err(err) => {
+                        let _ = worker_tx.send(WorkerMessage::Failed(err));
+                        return;
+                    },
+                };
+                let mut carry = String::new();
+                let mut pos = segment.start;
+                // later segments give up once an earlier match is known
+                while pos < segment.end && pos < best.load(Ordering::Relaxed) {
+                    let mut end = (pos + chunk_digits).min(segment.end);
+                    if end == segment.end {
+                        end = scan_end;
+                    }
+                    let chunk = match reader.read(pos..end) {
+                        Ok(chunk) => chunk,
+                        Err(err) => {
+                            let _ = worker_tx.send(WorkerMessage::Failed(err));
+                            return;
+                        },
+                    };
+                    let text = carry + chunk.as_str();
+                    let text_start = pos - (text.len() - chunk.len());
+                    // matches starting past the segment belong to the next one, or to nobody past the range
+                    if let Some(i) = text.find(pattern.as_str()).filter(|i| text_start + i < segment.end) {
+                        best.fetch_min(text_start + i, Ordering::Relaxed);
+                        let _ = worker_tx.send(WorkerMessage::Scanned(end.min(segment.end) - pos));
+                        return;
+                    }
+                    if worker_tx.send(WorkerMessage::Scanned(end.min(segment.end) - pos)).is_err() {
+                        return;
+                    }
+                    carry = text[text.len().saturating_sub(pattern.len() - 1)..].to_string();
+                    pos = end;
+                }
+            });
+        }
+        drop(worker_tx);
+
+        let meter = ProgressMeter::new(0, Some(total));
+        let mut scanned = 0usize;
+        for message in worker_rx {
+            match message {
+                WorkerMessage::Scanned(n) => {
+                    scanned += n;
+                    if ev_tx.send(meter.event(scanned)).is_err() {
+                        eprintln!("Main thread is dead");
+                        best.store(0, Ordering::Relaxed); // stops the workers
+                        return;
+                    }
+                },
+                WorkerMessage::Failed(err) => {
+                    best.store(0, Ordering::Relaxed);
+                    let _ = ev_tx.send(JobEvent::Error(err));
+                    return;
+                },
+            }
+        }
+
+        let found = best.load(Ordering::Relaxed);
+        if found != usize::MAX {
+            let _ = ev_tx.send(JobEvent::Match(found));
+        }
+        else {
+            let _ = ev_tx.send(JobEvent::NotFound { searched: range, reason });
+        }
+        let _ = ev_tx.send(JobEvent::Finished);
+    });
+    ev_rx
+}
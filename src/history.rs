@@ -0,0 +1,164 @@
+use std::cmp::Ordering;
+
+use crate::{jobs::JobKind, position::PositionConvention};
+
+// Where the digits containing the result came from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+pub enum ResultSource {
+    Cache,
+    Network,
+    File, // searched on disk without loading
+}
+
+impl ResultSource {
+    pub fn name(self) -> &'static str {
+        match self {
+            ResultSource::Cache => "cache",
+            ResultSource::Network => "network",
+            ResultSource::File => "file",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+pub struct HistoryEntry {
+    pub pattern: String,
+    pub kind: JobKind, // to re-run it the same way
+    pub index: Option<usize>, // cache offset
+    pub processed: usize, // positions searched
+    pub duration: f64, // seconds
+    pub source: ResultSource,
+}
+
+impl HistoryEntry {
+    // How it was searched: search, file search or find previous
+    pub fn mode(&self) -> &'static str {
+        self.kind.name()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HistoryColumn {
+    Order,
+    Pattern,
+    Mode,
+    Position,
+    Processed,
+    Duration,
+    Source,
+}
+
+impl HistoryColumn {
+    pub const ALL: [HistoryColumn; 7] = [
+        HistoryColumn::Order,
+        HistoryColumn::Pattern,
+        HistoryColumn::Mode,
+        HistoryColumn::Position,
+        HistoryColumn::Processed,
+        HistoryColumn::Duration,
+        HistoryColumn::Source,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HistoryColumn::Order => "#",
+            HistoryColumn::Pattern => "Pattern",
+            HistoryColumn::Mode => "Mode",
+            HistoryColumn::Position => "Position",
+            HistoryColumn::Processed => "Processed",
+            HistoryColumn::Duration => "Duration",
+            HistoryColumn::Source => "Source",
+        }
+    }
+}
+
+// Indices into `entries` ordered by `column`; entries without a result go last when sorting by position
+pub fn sorted_indices(entries: &[HistoryEntry], column: HistoryColumn, ascending: bool) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..entries.len()).collect();
+    indices.sort_by(|&a, &b| {
+        let (ea, eb) = (&entries[a], &entries[b]);
+        let ord = match column {
+            HistoryColumn::Order => a.cmp(&b),
+            HistoryColumn::Pattern => ea.pattern.len().cmp(&eb.pattern.len()).then_with(|| ea.pattern.cmp(&eb.pattern)),
+            HistoryColumn::Mode => ea.mode().cmp(eb.mode()),
+            HistoryColumn::Position => match (ea.index, eb.index) {
+                (Some(ia), Some(ib)) => ia.cmp(&ib),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            },
+            HistoryColumn::Processed => ea.processed.cmp(&eb.processed),
+            HistoryColumn::Duration => ea.duration.partial_cmp(&eb.duration).unwrap_or(Ordering::Equal),
+            HistoryColumn::Source => ea.source.name().cmp(eb.source.name()),
+        };
+        if ascending { ord } else { ord.reverse() }
+    });
+    indices
+}
+
+fn format_index(entry: &HistoryEntry, convention: PositionConvention, integer_digits: usize) -> Option<i64> {
+    entry.index.map(|index| convention.to_position(index, integer_digits))
+}
+
+// Quoted as RFC 4180 asks when it contains a separator, a quote or a line break
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    }
+    else {
+        text.to_string()
+    }
+}
+
+// Positions are written in the given convention, which is named in the header
+pub fn to_csv(entries: &[HistoryEntry], convention: PositionConvention, integer_digits: usize) -> String {
+    let mut csv = format!("pattern,mode,{},processed,duration_s,source\r\n", csv_field(format!("position ({})", convention.name()).as_str()));
+    for entry in entries {
+        let position = format_index(entry, convention, integer_digits).map_or(String::default(), |p| p.to_string());
+        csv.push_str(format!("{},{},{position},{},{:.3},{}\r\n", csv_field(&entry.pattern), csv_field(entry.mode()), entry.processed, entry.duration, csv_field(entry.source.name())).as_str());
+    }
+    csv
+}
+
+pub fn to_json(entries: &[HistoryEntry], convention: PositionConvention, integer_digits: usize) -> String {
+    let entries: Vec<serde_json::Value> = entries.iter().map(|entry| serde_json::json!({
+        "pattern": entry.pattern,
+        "mode": entry.mode(),
+        "position": format_index(entry, convention, integer_digits),
+        "processed": entry.processed,
+        "duration_s": entry.duration,
+        "source": entry.source.name(),
+    })).collect();
+    serde_json::to_string_pretty(&serde_json::json!({
+        "position_convention": convention.name(),
+        "searches": entries,
+    })).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(pattern: &str, index: Option<usize>) -> HistoryEntry {
+        HistoryEntry { pattern: pattern.to_string(), kind: JobKind::Search { pattern: pattern.to_string(), bounds: Default::default() }, index, processed: 10, duration: 0.5, source: ResultSource::Cache }
+    }
+
+    #[test]
+    fn csv() {
+        let csv = to_csv(&[entry("123", Some(5)), entry("4", None)], PositionConvention::AfterPointOneBased, 1);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "pattern,mode,\"position (After decimal point, 1-based)\",processed,duration_s,source");
+        assert_eq!(lines[1], "123,search,5,10,0.500,cache");
+        assert_eq!(lines[2], "4,search,,10,0.500,cache");
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field(""), "");
+    }
+}
@@ -0,0 +1,284 @@
+use std::{collections::BTreeMap, fmt};
+
+use crate::cache_format;
+
+// Reads digit files in the formats they're usually published in:
+//
+//  - our binary caches (see cache_format)
+//  - y-cruncher's compressed .ycd files (base 10, first block only)
+//  - text with a "3." prefix, digits grouped by spaces or line breaks every 10/50/100 digits
+//  - text with position labels at the start ("51: ...") or end ("... : 100") of the lines
+//  - title and page header lines, which have at least as many letters as digits
+//
+// Everything else that isn't a digit is skipped and counted in the report.
+
+const YCD_SIGNATURE: &[u8] = b"#Compressed Digit File";
+const YCD_DIGITS_PER_WORD: usize = 19;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DigitFormat {
+    Text,
+    Ycd,
+    Binary,
+}
+
+#[derive(Clone, Debug)]
+pub struct ImportReport {
+    pub format: DigitFormat,
+    pub digits: usize,
+    pub integer_digits: Option<usize>,    // when the file marks the decimal point
+    pub header_lines: usize,
+    pub position_labels: usize,
+    pub skipped: BTreeMap<char, usize>,   // unexpected characters
+}
+
+impl ImportReport {
+    pub fn new(format: DigitFormat) -> Self {
+        Self {
+            format,
+            digits: 0,
+            integer_digits: None,
+            header_lines: 0,
+            position_labels: 0,
+            skipped: BTreeMap::new(),
+        }
+    }
+
+    pub fn skipped_chars(&self) -> usize {
+        self.skipped.values().sum()
+    }
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = match self.format {
+            DigitFormat::Text => "text",
+            DigitFormat::Ycd => "y-cruncher .ycd",
+            DigitFormat::Binary => "binary cache",
+        };
+        write!(f, "Read {} digits ({format})", self.digits)?;
+        if self.header_lines > 0 {
+            write!(f, ", skipped {} header lines", self.header_lines)?;
+        }
+        if self.position_labels > 0 {
+            write!(f, ", skipped {} position labels", self.position_labels)?;
+        }
+        if !self.skipped.is_empty() {
+            let chars: Vec<String> = self.skipped.iter().map(|(c, n)| format!("{c:?} x{n}")).collect();
+            write!(f, ", skipped {} unexpected characters: {}", self.skipped_chars(), chars.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+// `complete` is false when `bytes` is only the start of a file; a trailing partial line is then ignored
+pub fn import_digits(bytes: &[u8], complete: bool) -> Result<(String, ImportReport), String> {
+    let (digits, report) = if cache_format::is_binary_cache(bytes) {
+        let (header, digits) = cache_format::decode(bytes, complete).map_err(|err| err.to_string())?;
+        let mut report = ImportReport::new(DigitFormat::Binary);
+        report.digits = digits.len();
+        report.integer_digits = Some(header.integer_digits);
+        (digits, report)
+    }
+    else if bytes.starts_with(YCD_SIGNATURE) {
+        import_ycd(bytes, complete)?
+    }
+    else {
+        let text = String::from_utf8_lossy(bytes);
+        let text = match text.rfind('\n') {
+            Some(end) if !complete => &text[..end],
+            _ => &text[..],
+        };
+        import_text(text)
+    };
+    if digits.is_empty() && complete {
+        return Err("No digits found".to_string());
+    }
+    Ok((digits, report))
+}
+
+fn import_text(text: &str) -> (String, ImportReport) {
+    let mut report = ImportReport::new(DigitFormat::Text);
+    let mut digits = String::with_capacity(text.len());
+
+    for line in text.lines() {
+        let mut line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        // titles and page headers; a stray letter in a line of digits is reported instead
+        let letters = line.chars().filter(|c| c.is_alphabetic()).count();
+        if letters > 0 && letters >= line.chars().filter(char::is_ascii_digit).count() {
+            report.header_lines += 1;
+            continue;
+        }
+        // a label on either side of the digits, or both; with one colon the shorter side is the label
+        let parts: Vec<&str> = line.split(':').collect();
+        match parts[..] {
+            [start, rest, end] if is_number(start) && is_number(end) => {
+                report.position_labels += 2;
+                line = rest;
+            },
+            [left, right] if is_number(left) && left.trim().len() <= right.trim().len() => {
+                report.position_labels += 1;
+                line = right;
+            },
+            [left, right] if is_number(right) => {
+                report.position_labels += 1;
+                line = left;
+            },
+            _ => {},
+        }
+
+        for c in line.chars() {
+            match c {
+                '0'..='9' => digits.push(c),
+                '.' if report.integer_digits.is_none() && !digits.is_empty() => report.integer_digits = Some(digits.len()),
+                c if c.is_whitespace() => {},
+                c => *report.skipped.entry(c).or_insert(0) += 1,
+            }
+        }
+    }
+
+    report.digits = digits.len();
+    (digits, report)
+}
+
+fn is_number(text: &str) -> bool {
+    let text = text.trim();
+    !text.is_empty() && text.chars().all(|c| c.is_ascii_digit())
+}
+
+// Header of "Key:<tab>Value" lines up to "EndHeader", a zero byte, then little-endian
+// 64-bit words holding 19 digits each. The digits are the ones after the decimal point.
+fn import_ycd(bytes: &[u8], complete: bool) -> Result<(String, ImportReport), String> {
+    let header_end = bytes.windows(9).position(|w| w == b"EndHeader")
+        .and_then(|end| bytes[end..].iter().position(|&b| b == 0).map(|zero| (end, end + zero + 1)));
+    let (end, data_start) = match header_end {
+        Some(header_end) => header_end,
+        None if !complete => return Ok((String::new(), ImportReport::new(DigitFormat::Ycd))),
+        None => return Err("Incomplete .ycd header".to_string()),
+    };
+    let header = String::from_utf8_lossy(&bytes[..end]);
+
+    let mut fields = BTreeMap::new();
+    for line in header.lines() {
+        if let Some((key, value)) = line.split_once(':') {
+            fields.insert(key.trim(), value.trim());
+        }
+    }
+    let field = |key: &str| fields.get(key).copied().ok_or(format!("Missing {key} in the .ycd header"));
+    let number = |key: &str| field(key)?.parse::<usize>().map_err(|_| format!("Invalid {key} in the .ycd header"));
+
+    if field("Base")? != "10" {
+        return Err(format!("Only decimal .ycd files can be read, this one is base {}", field("Base")?));
+    }
+    let block_id = number("BlockID")?;
+    if block_id != 0 {
+        return Err(format!("This is block {block_id} of a multi-file .ycd, read block 0 first"));
+    }
+    let first_digits = field("FirstDigits")?;
+    let (integer, fraction) = first_digits.split_once('.').ok_or("FirstDigits has no decimal point")?;
+    if !integer.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("FirstDigits has an invalid integer part \"{integer}\""));
+    }
+    let total = number("TotalDigits")?;
+    let block_size = number("Blocksize")?;
+    let in_block = if total == 0 { block_size } else { block_size.min(total) };
+
+    let words = (bytes.len() - data_start) / 8;
+    let mut digits = String::with_capacity(integer.len() + in_block.min(words * YCD_DIGITS_PER_WORD));
+    digits.push_str(integer);
+    for word in bytes[data_start..].chunks_exact(8) {
+        if digits.len() - integer.len() >= in_block {
+            break;
+        }
+        let value = u64::from_le_bytes(word.try_into().unwrap());
+        if value >= 10_000_000_000_000_000_000 {
+            return Err(format!("Corrupt .ycd data at byte {}", data_start + (digits.len() - integer.len()) / YCD_DIGITS_PER_WORD * 8));
+        }
+        digits.push_str(format!("{value:019}").as_str());
+    }
+    digits.truncate(integer.len() + in_block);
+
+    // the header repeats the first digits, which catches wrong offsets and other constants.
+    // Bytes, so that a header with other characters doesn't get sliced inside one.
+    let decoded = &digits.as_bytes()[integer.len()..];
+    let check = fraction.len().min(decoded.len());
+    if decoded[..check] != fraction.as_bytes()[..check] {
+        return Err("The .ycd data doesn't match the FirstDigits in its header".to_string());
+    }
+
+    let mut report = ImportReport::new(DigitFormat::Ycd);
+    report.digits = digits.len();
+    report.integer_digits = Some(integer.len());
+    Ok((digits, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PI_WORDS: [u64; 2] = [1415926535897932384, 6264338327950288419];
+
+    fn ycd(first_digits: &str, total: usize, words: &[u64]) -> Vec<u8> {
+        let header = format!("#Compressed Digit File\r\n\r\nBase:\t10\r\n\r\nFirstDigits:\t{first_digits}\r\n\r\nTotalDigits:\t{total}\r\n\r\nBlocksize:\t1000000\r\nBlockID:\t0\r\n\r\nEndHeader\r\n\r\n");
+        let mut bytes = header.into_bytes();
+        bytes.push(0u8);
+        for word in words {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn text() {
+        let (digits, report) = import_digits(b"Pi to 20 digits\n3.1415926535\n8979323846\n", true).unwrap();
+        assert_eq!(digits, "314159265358979323846");
+        assert_eq!(report.integer_digits, Some(1));
+        assert_eq!(report.header_lines, 1);
+        assert!(report.skipped.is_empty());
+
+        let (digits, report) = import_text("1: 1415926535 : 10\n11: 8979x323846\n");
+        assert_eq!(digits, "14159265358979323846");
+        assert_eq!(report.position_labels, 3);
+        assert_eq!(report.skipped.get(&'x'), Some(&1));
+
+        // a partial last line is left for the next read
+        assert_eq!(import_digits(b"3.14\n15", false).unwrap().0, "314");
+    }
+
+    #[test]
+    fn empty() {
+        assert!(import_digits(b"", true).is_err());
+        assert!(import_digits(b"Pi\n", true).is_err());
+        assert_eq!(import_digits(b"", false).unwrap().0, "");
+        assert_eq!(import_text("").1.digits, 0);
+    }
+
+    #[test]
+    fn ycd_digits() {
+        let (digits, report) = import_digits(&ycd("3.14159265358979323846", 30, &PI_WORDS), true).unwrap();
+        assert_eq!(digits, "3141592653589793238462643383279");
+        assert_eq!(report.format, DigitFormat::Ycd);
+        assert_eq!(report.integer_digits, Some(1));
+
+        // the header can say more than the data has
+        let (digits, _) = import_digits(&ycd("3.14159265358979323846", 100, &PI_WORDS[..1]), true).unwrap();
+        assert_eq!(digits, "31415926535897932384");
+    }
+
+    #[test]
+    fn ycd_errors() {
+        assert!(import_digits(&ycd("3.14159265358979323847", 30, &PI_WORDS), true).is_err());
+        assert!(import_digits(&ycd("3.1415926535897932384", 30, &[u64::MAX]), true).is_err());
+        // characters that aren't digits are rejected, not sliced
+        assert!(import_digits(&ycd("3.14\u{661}", 30, &PI_WORDS), true).is_err());
+        assert!(import_digits(&ycd("\u{663}.14", 30, &PI_WORDS), true).is_err());
+
+        let header = ycd("3.14", 30, &[]);
+        let cut = &header[..header.len() - 20];
+        assert!(import_digits(cut, true).is_err());
+        assert_eq!(import_digits(cut, false).unwrap().0, "");
+    }
+}
@@ -0,0 +1,328 @@
+use instant::Instant;
+use std::{ops::Range, sync::mpsc::{Receiver, TryRecvError}, time::Duration};
+
+use crate::analysis::Finding;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Progress {
+    pub done: usize,          // absolute number of digits loaded/processed
+    pub total: Option<usize>, // None if the job has no known end
+    pub bytes: usize,         // bytes received from the network by this job
+    pub rate: f64,            // digits per second since the job started
+}
+
+impl Progress {
+    pub fn fraction(&self) -> Option<f32> {
+        match self.total {
+            Some(0) => Some(1f32),
+            Some(total) => Some((self.done as f32 / total as f32).min(1f32)),
+            None => None,
+        }
+    }
+
+    pub fn eta(&self) -> Option<Duration> {
+        let total = self.total?;
+        if self.rate <= 0f64 {
+            return None;
+        }
+        let left = total.saturating_sub(self.done);
+        Duration::try_from_secs_f64(left as f64 / self.rate).ok()
+    }
+}
+
+// Where a search may look: matches start in `start..end`, and the search gives up after
+// `max_digits` match positions or `time_limit`. The default searches until it finds a match.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+pub struct SearchBounds {
+    pub start: usize,
+    pub end: Option<usize>,
+    pub max_digits: Option<usize>,
+    pub time_limit: Option<Duration>,
+}
+
+impl SearchBounds {
+    pub fn from(start: usize) -> Self {
+        Self { start, ..Self::default() }
+    }
+
+    // The same search continued after a match at `index`, the next match may overlap it
+    pub fn after(&self, index: usize) -> Self {
+        Self { start: index + 1, ..self.clone() }
+    }
+
+    // End of the positions a match may start at
+    pub fn limit(&self) -> Option<usize> {
+        let budget = self.max_digits.map(|n| self.start.saturating_add(n));
+        match (self.end, budget) {
+            (Some(end), Some(budget)) => Some(end.min(budget)),
+            (end, budget) => end.or(budget),
+        }
+    }
+
+    // Digits to read, a match starting right before the limit runs past it
+    pub fn scan_end(&self, pattern_len: usize) -> Option<usize> {
+        self.limit().map(|limit| limit.max(self.start).saturating_add(pattern_len.saturating_sub(1)))
+    }
+
+    // Why a search that read up to `scan_end` stopped
+    pub fn end_reason(&self) -> StopReason {
+        match (self.end, self.max_digits) {
+            (Some(end), Some(n)) if self.start.saturating_add(n) < end => StopReason::DigitBudget,
+            (None, Some(_)) => StopReason::DigitBudget,
+            _ => StopReason::EndOfRange,
+        }
+    }
+
+    pub fn timed_out(&self, started: Instant) -> bool {
+        self.time_limit.is_some_and(|limit| started.elapsed() >= limit)
+    }
+
+    // Match positions ruled out once the digits before `scanned` were searched
+    pub fn searched(&self, scanned: usize, pattern_len: usize) -> Range<usize> {
+        self.start..(scanned + 1).saturating_sub(pattern_len.max(1)).max(self.start)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    EndOfRange,
+    DigitBudget,
+    TimeLimit,
+    EndOfFile,
+}
+
+impl StopReason {
+    pub fn name(self) -> &'static str {
+        match self {
+            StopReason::EndOfRange => "end of range",
+            StopReason::DigitBudget => "digit budget used up",
+            StopReason::TimeLimit => "time limit reached",
+            StopReason::EndOfFile => "end of file",
+        }
+    }
+}
+
+// Result of a bounded search
+#[derive(Clone, Debug, PartialEq)]
+pub enum SearchOutcome {
+    Found(usize),
+    NotFound { searched: Range<usize>, reason: StopReason },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JobEvent {
+    Started { total: Option<usize> },
+    Progress(Progress),
+    Match(usize),
+    NotFound { searched: Range<usize>, reason: StopReason }, // no match starts in `searched`
+    Finding(Finding), // analysis jobs report everything they find
+    Warning(String),
+    Error(String),
+    Finished,
+}
+
+// Used by the job threads to turn raw counters into `Progress` events
+pub struct ProgressMeter {
+    start: Instant,
+    initial: usize,
+    total: Option<usize>,
+    bytes: usize,
+}
+
+impl ProgressMeter {
+    pub fn new(initial: usize, total: Option<usize>) -> Self {
+        Self {
+            start: Instant::now(),
+            initial,
+            total,
+            bytes: 0usize,
+        }
+    }
+
+    pub fn add_bytes(&mut self, bytes: usize) {
+        self.bytes += bytes;
+    }
+
+    pub fn progress(&self, done: usize) -> Progress {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let rate = if elapsed > 0f64 {
+            done.saturating_sub(self.initial) as f64 / elapsed
+        }
+        else {
+            0f64
+        };
+        Progress {
+            done,
+            total: self.total,
+            bytes: self.bytes,
+            rate,
+        }
+    }
+
+    pub fn event(&self, done: usize) -> JobEvent {
+        JobEvent::Progress(self.progress(done))
+    }
+}
+
+// Frontend side: everything a UI needs to know about a running job
+#[derive(Default)]
+pub struct JobStatus {
+    pub started: bool,
+    pub progress: Option<Progress>,
+    pub matched: Option<usize>,
+    pub not_found: Option<(Range<usize>, StopReason)>,
+    pub findings: Vec<Finding>,
+    pub warnings: Vec<String>,
+    pub error: Option<String>,
+    pub finished: bool,
+}
+
+impl JobStatus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn handle(&mut self, event: JobEvent) {
+        match event {
+            JobEvent::Started { total } => {
+                self.started = true;
+                self.progress = Some(Progress { done: 0usize, total, bytes: 0usize, rate: 0f64 });
+            },
+            JobEvent::Progress(progress) => self.progress = Some(progress),
+            JobEvent::Match(index) => self.matched = Some(index),
+            JobEvent::NotFound { searched, reason } => self.not_found = Some((searched, reason)),
+            JobEvent::Finding(finding) => self.findings.push(finding),
+            JobEvent::Warning(warning) => self.warnings.push(warning),
+            JobEvent::Error(error) => {
+                self.error = Some(error);
+                self.finished = true;
+            },
+            JobEvent::Finished => self.finished = true,
+        }
+    }
+
+    // Drains all pending events, returns false if the job thread is gone without finishing
+    pub fn poll(&mut self, events: &Receiver<JobEvent>) -> bool {
+        loop {
+            match events.try_recv() {
+                Ok(event) => self.handle(event),
+                Err(TryRecvError::Empty) => return true,
+                Err(TryRecvError::Disconnected) => {
+                    if !self.finished {
+                        self.error = Some("Job thread is dead".to_string());
+                        self.finished = true;
+                        return false;
+                    }
+                    return true;
+                },
+            }
+        }
+    }
+
+    pub fn done(&self) -> usize {
+        self.progress.as_ref().map_or(0usize, |p| p.done)
+    }
+}
+
+// Last match starting before `before`, among digits that are already loaded
+pub fn find_previous(digits: &str, pattern: &str, before: usize) -> Option<usize> {
+    if pattern.is_empty() {
+        return None;
+    }
+    let end = before.saturating_add(pattern.len() - 1).min(digits.len());
+    digits[..end].rfind(pattern)
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}h {:02}m {:02}s", secs / 3600, secs / 60 % 60, secs % 60)
+    }
+    else if secs >= 60 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    }
+    else {
+        format!("{}.{}s", secs, duration.subsec_millis() / 100)
+    }
+}
+
+pub fn format_progress(progress: &Progress) -> String {
+    let mut text = match progress.total {
+        Some(total) => format!("{}/{} ({}%)", progress.done, total, (progress.fraction().unwrap_or(0f32) * 100f32) as u32),
+        None => format!("{}", progress.done),
+    };
+    text.push_str(format!(", {:.0} digits/s, {} KiB received", progress.rate, progress.bytes / 1024).as_str());
+    if let Some(eta) = progress.eta() {
+        text.push_str(format!(", ETA {}", format_duration(eta)).as_str());
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(start: usize, end: Option<usize>, max_digits: Option<usize>) -> SearchBounds {
+        SearchBounds { start, end, max_digits, time_limit: None }
+    }
+
+    #[test]
+    fn limits() {
+        assert_eq!(SearchBounds::default().limit(), None);
+        assert_eq!(SearchBounds::default().scan_end(3), None);
+        assert_eq!(bounds(10, Some(100), None).limit(), Some(100));
+        assert_eq!(bounds(10, None, Some(50)).limit(), Some(60));
+        assert_eq!(bounds(10, Some(40), Some(50)).limit(), Some(40));
+        assert_eq!(bounds(10, Some(100), Some(50)).limit(), Some(60));
+        assert_eq!(bounds(usize::MAX - 1, None, Some(50)).limit(), Some(usize::MAX));
+
+        assert_eq!(bounds(10, Some(40), Some(50)).end_reason(), StopReason::EndOfRange);
+        assert_eq!(bounds(10, Some(100), Some(50)).end_reason(), StopReason::DigitBudget);
+        assert_eq!(bounds(10, None, Some(50)).end_reason(), StopReason::DigitBudget);
+        assert_eq!(bounds(10, Some(100), None).end_reason(), StopReason::EndOfRange);
+    }
+
+    #[test]
+    fn scan_end() {
+        // a match starting at the last allowed position reads past the limit
+        assert_eq!(bounds(0, Some(100), None).scan_end(4), Some(103));
+        assert_eq!(bounds(0, Some(100), None).scan_end(1), Some(100));
+        assert_eq!(bounds(0, Some(100), None).scan_end(0), Some(100));
+        // an end before the start reads nothing past the start
+        assert_eq!(bounds(50, Some(10), None).scan_end(3), Some(52));
+        assert_eq!(bounds(0, Some(usize::MAX), None).scan_end(3), Some(usize::MAX));
+    }
+
+    #[test]
+    fn searched() {
+        let bounds = bounds(10, Some(100), None);
+        assert_eq!(bounds.searched(103, 4), 10..100);
+        assert_eq!(bounds.searched(50, 1), 10..50);
+        // too few digits read for any match
+        assert_eq!(bounds.searched(12, 4), 10..10);
+        assert_eq!(bounds.searched(0, 4), 10..10);
+        assert_eq!(SearchBounds::default().searched(5, 0), 0..5);
+
+        let after = bounds.after(42);
+        assert_eq!(after.start, 43);
+        assert_eq!(after.end, Some(100));
+    }
+
+    #[test]
+    fn previous() {
+        let digits = "1415926535897932384626433832795";
+        assert_eq!(find_previous(digits, "35", 10), Some(8));
+        assert_eq!(find_previous(digits, "35", 8), None);
+        // a match may overlap the one it steps back from
+        assert_eq!(find_previous("1111", "11", 2), Some(1));
+        assert_eq!(find_previous(digits, "14", 1), Some(0));
+        assert_eq!(find_previous(digits, "14", 0), None);
+        // past the end of the digits, the last match in them
+        assert_eq!(find_previous(digits, "79", 1000), Some(28));
+        assert_eq!(find_previous("", "1", 5), None);
+        assert_eq!(find_previous(digits, "", 5), None);
+        assert_eq!(find_previous("14", "1415", 3), None);
+        assert_eq!(find_previous(digits, "95", usize::MAX), Some(29));
+    }
+}
@@ -0,0 +1,341 @@
+use instant::Instant;
+use std::{sync::mpsc::{self, Receiver}, time::Duration};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{ops::Range, path::PathBuf, sync::Arc};
+
+use crate::{analysis::Analysis, history::*, job::*};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{file_search::*, search::Search};
+#[cfg(target_arch = "wasm32")]
+use crate::web::Search;
+
+pub const DEFAULT_MAX_RUNNING: usize = 3;
+
+pub type JobId = usize;
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+pub enum JobKind {
+    Preload { count: usize },
+    Search { pattern: String, bounds: SearchBounds },
+    FindPrevious { pattern: String, before: usize }, // in the cached digits
+    Analysis { analysis: Analysis, bounds: SearchBounds },
+    #[cfg(not(target_arch = "wasm32"))]
+    FileSearch { path: PathBuf, pattern: String, range: Option<Range<usize>> },
+}
+
+impl JobKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobKind::Preload { .. } => "preload",
+            JobKind::Search { .. } => "search",
+            JobKind::FindPrevious { .. } => "find previous",
+            JobKind::Analysis { analysis, .. } => analysis.name(),
+            #[cfg(not(target_arch = "wasm32"))]
+            JobKind::FileSearch { .. } => "file search",
+        }
+    }
+
+    pub fn pattern(&self) -> Option<&str> {
+        match self {
+            JobKind::Preload { .. } | JobKind::Analysis { .. } => None,
+            JobKind::Search { pattern, .. } | JobKind::FindPrevious { pattern, .. } => Some(pattern.as_str()),
+            #[cfg(not(target_arch = "wasm32"))]
+            JobKind::FileSearch { pattern, .. } => Some(pattern.as_str()),
+        }
+    }
+
+    // Short form for job lists
+    pub fn describe(&self) -> String {
+        match self {
+            JobKind::Preload { count } => format!("{count} digits"),
+            JobKind::Search { pattern, bounds } if *bounds == SearchBounds::default() => pattern.clone(),
+            JobKind::Search { pattern, bounds } => match bounds.limit() {
+                Some(limit) => format!("{pattern} in {}..{limit}", bounds.start),
+                None => format!("{pattern} from {}", bounds.start),
+            },
+            JobKind::FindPrevious { pattern, before } => format!("{pattern} before {before}"),
+            JobKind::Analysis { analysis, bounds } => {
+                let range = match bounds.limit() {
+                    Some(limit) => format!("{}..{limit}", bounds.start),
+                    None => format!("from {}", bounds.start),
+                };
+                match analysis.describe() {
+                    Some(query) => format!("{query}, {range}"),
+                    None => format!("digits {range}"),
+                }
+            },
+            #[cfg(not(target_arch = "wasm32"))]
+            JobKind::FileSearch { path, pattern, .. } => format!("{pattern} in {}", path.file_name().map_or(String::default(), |n| n.to_string_lossy().to_string())),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JobState {
+    Queued,
+    Running,
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn name(self) -> &'static str {
+        match self {
+            JobState::Queued => "queued",
+            JobState::Running => "running",
+            JobState::Finished => "finished",
+            JobState::Failed => "failed",
+            JobState::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn is_done(self) -> bool {
+        !matches!(self, JobState::Queued | JobState::Running)
+    }
+}
+
+pub struct Job {
+    pub id: JobId,
+    pub kind: JobKind,
+    pub priority: i32, // higher starts first, then queue order
+    pub state: JobState,
+    pub status: JobStatus,
+    pub cached: usize, // digits loaded when the job started
+    started: Option<Instant>,
+    duration: Option<Duration>,
+    events: Option<Receiver<JobEvent>>,
+}
+
+impl Job {
+    pub fn elapsed(&self) -> Duration {
+        self.duration.or_else(|| self.started.map(|started| started.elapsed())).unwrap_or_default()
+    }
+
+    // Where a search found its result, or would have
+    pub fn source(&self) -> ResultSource {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let JobKind::FileSearch { .. } = self.kind {
+            return ResultSource::File;
+        }
+        if let JobKind::FindPrevious { .. } = self.kind {
+            return ResultSource::Cache;
+        }
+        let pattern_len = self.kind.pattern().map_or(0usize, str::len);
+        match self.status.matched {
+            Some(index) if index + pattern_len <= self.cached => ResultSource::Cache,
+            Some(_) => ResultSource::Network,
+            None if self.status.done() <= self.cached => ResultSource::Cache,
+            None => ResultSource::Network,
+        }
+    }
+
+    // First position where a match may start, None for preloads
+    pub fn search_start(&self) -> Option<usize> {
+        match &self.kind {
+            JobKind::Preload { .. } | JobKind::Analysis { .. } => None,
+            JobKind::Search { bounds, .. } => Some(bounds.start),
+            JobKind::FindPrevious { .. } => Some(0usize),
+            #[cfg(not(target_arch = "wasm32"))]
+            JobKind::FileSearch { range, .. } => Some(range.as_ref().map_or(0usize, |range| range.start)),
+        }
+    }
+
+    // Positions searched: searches count from their start, file searches count from 0 already,
+    // Find previous counts back from where it started
+    pub fn processed(&self) -> usize {
+        match &self.kind {
+            JobKind::Search { bounds, .. } | JobKind::Analysis { bounds, .. } => self.status.done().saturating_sub(bounds.start),
+            JobKind::FindPrevious { before, .. } => before - self.status.matched.unwrap_or(0usize),
+            _ => self.status.done(),
+        }
+    }
+
+    // Search results go to the history, failed and cancelled searches don't
+    pub fn history_entry(&self) -> Option<HistoryEntry> {
+        if self.state != JobState::Finished {
+            return None;
+        }
+        Some(HistoryEntry {
+            pattern: self.kind.pattern()?.to_string(),
+            kind: self.kind.clone(),
+            index: self.status.matched,
+            processed: self.processed(),
+            duration: self.elapsed().as_secs_f64(),
+            source: self.source(),
+        })
+    }
+}
+
+// Queue of preloads and searches. Up to `max_running` of them run at once, all on the same
+// `Search`, so they share its cache, request limits and downloads.
+pub struct JobManager {
+    jobs: Vec<Job>, // in queue order
+    next_id: JobId,
+    max_running: usize,
+}
+
+impl Default for JobManager {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_RUNNING)
+    }
+}
+
+impl JobManager {
+    pub fn new(max_running: usize) -> Self {
+        Self {
+            jobs: Vec::new(),
+            next_id: 1,
+            max_running: max_running.max(1),
+        }
+    }
+
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    pub fn get(&self, id: JobId) -> Option<&Job> {
+        self.jobs.iter().find(|job| job.id == id)
+    }
+
+    fn position(&self, id: JobId) -> Option<usize> {
+        self.jobs.iter().position(|job| job.id == id)
+    }
+
+    pub fn max_running(&self) -> usize {
+        self.max_running
+    }
+
+    pub fn set_max_running(&mut self, max_running: usize) {
+        self.max_running = max_running.max(1);
+    }
+
+    // No job is running or waiting
+    pub fn is_idle(&self) -> bool {
+        self.jobs.iter().all(|job| job.state.is_done())
+    }
+
+    pub fn running(&self) -> impl Iterator<Item = &Job> {
+        self.jobs.iter().filter(|job| job.state == JobState::Running)
+    }
+
+    // Starts with the next `update`
+    pub fn submit(&mut self, kind: JobKind, priority: i32) -> JobId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.jobs.push(Job {
+            id,
+            kind,
+            priority,
+            state: JobState::Queued,
+            status: JobStatus::new(),
+            cached: 0usize,
+            started: None,
+            duration: None,
+            events: None,
+        });
+        id
+    }
+
+    // Dropping the events stops a running job, its downloaded digits stay in the cache
+    pub fn cancel(&mut self, id: JobId) -> bool {
+        let job = match self.jobs.iter_mut().find(|job| job.id == id) {
+            Some(job) if !job.state.is_done() => job,
+            _ => return false,
+        };
+        job.events = None;
+        job.duration = job.started.map(|started| started.elapsed());
+        job.state = JobState::Cancelled;
+        true
+    }
+
+    pub fn set_priority(&mut self, id: JobId, priority: i32) {
+        if let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) {
+            job.priority = priority;
+        }
+    }
+
+    // Moves a job one place towards the front (-1) or the back (1) of the queue
+    pub fn move_job(&mut self, id: JobId, direction: isize) {
+        if let Some(i) = self.position(id) {
+            let j = i.saturating_add_signed(direction);
+            if j < self.jobs.len() {
+                self.jobs.swap(i, j);
+            }
+        }
+    }
+
+    pub fn remove_done(&mut self) {
+        self.jobs.retain(|job| !job.state.is_done());
+    }
+
+    // Polls the running jobs and starts queued ones in their place.
+    // Returns the jobs that finished or failed since the last call.
+    pub fn update(&mut self, search: &mut Search) -> Vec<JobId> {
+        let mut done = Vec::new();
+        for job in self.jobs.iter_mut().filter(|job| job.state == JobState::Running) {
+            job.status.poll(job.events.as_ref().unwrap());
+            if job.status.finished {
+                job.events = None;
+                job.duration = job.started.map(|started| started.elapsed());
+                job.state = if job.status.error.is_some() { JobState::Failed } else { JobState::Finished };
+                done.push(job.id);
+            }
+        }
+        search.join_finished();
+
+        while self.running().count() < self.max_running {
+            // highest priority first, the earlier one among equals
+            let next = self.jobs.iter().enumerate()
+                .filter(|(_, job)| job.state == JobState::Queued)
+                .max_by_key(|(i, job)| (job.priority, std::cmp::Reverse(*i)))
+                .map(|(i, _)| i);
+            match next {
+                Some(i) => {
+                    if let Err(err) = start(&mut self.jobs[i], search) {
+                        let job = &mut self.jobs[i];
+                        job.status.error = Some(err);
+                        job.status.finished = true;
+                        job.state = JobState::Failed;
+                        done.push(job.id);
+                    }
+                },
+                None => break,
+            }
+        }
+        done
+    }
+}
+
+fn start(job: &mut Job, search: &mut Search) -> Result<(), String> {
+    job.cached = search.digits_loaded();
+    let events = match &job.kind {
+        JobKind::Preload { count } => search.preload(*count),
+        JobKind::Search { pattern, bounds } => search.search_in(pattern.as_str(), bounds.clone()),
+        JobKind::FindPrevious { pattern, before } => find_previous_job(search.find_previous(pattern.as_str(), *before), *before),
+        JobKind::Analysis { analysis, bounds } => search.analyze(analysis.analyzer(search.integer_digits()), bounds.clone()),
+        #[cfg(not(target_arch = "wasm32"))]
+        JobKind::FileSearch { path, pattern, range } => {
+            let file = DigitFile::open(path)?;
+            job.cached = 0usize;
+            search_file(Arc::new(file), pattern.as_str(), range.clone(), search.get_config().workers, DEFAULT_CHUNK_DIGITS)
+        },
+    };
+    job.events = Some(events);
+    job.started = Some(Instant::now());
+    job.state = JobState::Running;
+    Ok(())
+}
+
+// Find previous only looks at the cached digits, so its result is known right away
+fn find_previous_job(found: Option<usize>, before: usize) -> Receiver<JobEvent> {
+    let (tx, rx) = mpsc::channel();
+    let _ = tx.send(JobEvent::Started { total: None });
+    let _ = tx.send(match found {
+        Some(index) => JobEvent::Match(index),
+        None => JobEvent::NotFound { searched: 0..before, reason: StopReason::EndOfRange },
+    });
+    let _ = tx.send(JobEvent::Finished);
+    rx
+}
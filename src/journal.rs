@@ -0,0 +1,183 @@
+use std::{collections::BTreeMap, fs::{self, File, OpenOptions}, io::{self, Write}, ops::Range, path::{Path, PathBuf}};
+
+// Preload journal: every downloaded chunk is appended as a "<start> <digits>" line as soon as it arrives,
+// so an interrupted preload can be resumed without downloading those chunks again.
+// A line cut short by a crash is ignored when reading.
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+}
+
+impl Journal {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // On disk when it returns, a crash can't lose a chunk the preload has moved past
+    pub fn record(&mut self, start: usize, digits: &str) -> io::Result<()> {
+        self.file.write_all(format!("{start} {digits}\n").as_bytes())?;
+        self.file.sync_data()
+    }
+
+    // Chunks by start position, a missing file is an empty journal
+    pub fn read(path: &Path) -> io::Result<BTreeMap<usize, String>> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(err) => return Err(err),
+        };
+
+        let mut chunks = BTreeMap::new();
+        for line in text.split_inclusive('\n') {
+            let line = match line.strip_suffix('\n') {
+                Some(line) => line,
+                None => break, // unfinished write
+            };
+            let (start, digits) = match line.split_once(' ') {
+                Some(chunk) => chunk,
+                None => continue,
+            };
+            let start = match start.parse::<usize>() {
+                Ok(start) => start,
+                Err(_) => continue,
+            };
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                continue;
+            }
+            let longer = chunks.get(&start).map_or(true, |c: &String| c.len() < digits.len());
+            if longer {
+                chunks.insert(start, digits.to_string());
+            }
+        }
+        Ok(chunks)
+    }
+
+    pub fn remove(path: &Path) -> io::Result<()> {
+        match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Merges the chunks into sorted, non-overlapping ranges
+pub fn completed_ranges(chunks: &BTreeMap<usize, String>) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for (&start, digits) in chunks {
+        let end = start + digits.len();
+        match ranges.last_mut() {
+            Some(last) if start <= last.end => last.end = last.end.max(end),
+            _ => ranges.push(start..end),
+        }
+    }
+    ranges
+}
+
+// Parts of `range` not covered by `completed` (sorted, non-overlapping)
+pub fn missing_ranges(range: Range<usize>, completed: &[Range<usize>]) -> Vec<Range<usize>> {
+    let mut missing = Vec::new();
+    let mut pos = range.start;
+    for done in completed {
+        if done.end <= pos {
+            continue;
+        }
+        if done.start >= range.end {
+            break;
+        }
+        if done.start > pos {
+            missing.push(pos..done.start);
+        }
+        pos = pos.max(done.end);
+    }
+    if pos < range.end {
+        missing.push(pos..range.end);
+    }
+    missing
+}
+
+// Moves chunks that continue the cache from `pending` into `digits`, returns the number of digits added
+pub fn promote_chunks(digits: &mut String, pending: &mut BTreeMap<usize, String>) -> usize {
+    let before = digits.len();
+    while let Some((&start, _)) = pending.iter().next() {
+        if start > digits.len() {
+            break;
+        }
+        let chunk = pending.remove(&start).unwrap();
+        if start + chunk.len() > digits.len() {
+            let skip = digits.len() - start;
+            digits.push_str(&chunk[skip..]);
+        }
+    }
+    digits.len() - before
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunks(list: &[(usize, &str)]) -> BTreeMap<usize, String> {
+        list.iter().map(|&(start, digits)| (start, digits.to_string())).collect()
+    }
+
+    fn pairs(ranges: &[Range<usize>]) -> Vec<(usize, usize)> {
+        ranges.iter().map(|range| (range.start, range.end)).collect()
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(completed_ranges(&BTreeMap::new()), []);
+        // touching and overlapping chunks merge, a gap doesn't
+        let completed = completed_ranges(&chunks(&[(0, "314"), (3, "15"), (4, "59"), (10, "35")]));
+        assert_eq!(pairs(&completed), [(0, 6), (10, 12)]);
+
+        assert_eq!(pairs(&missing_ranges(0..15, &completed)), [(6, 10), (12, 15)]);
+        assert_eq!(pairs(&missing_ranges(2..11, &completed)), [(6, 10)]);
+        assert_eq!(missing_ranges(0..6, &completed), []);
+        assert_eq!(pairs(&missing_ranges(20..30, &completed)), [(20, 30)]);
+        assert_eq!(missing_ranges(5..5, &completed), []);
+        assert_eq!(pairs(&missing_ranges(0..3, &[])), [(0, 3)]);
+    }
+
+    #[test]
+    fn promote() {
+        let mut digits = "314".to_string();
+        let mut pending = chunks(&[(2, "4159"), (6, "26"), (9, "35")]);
+        // chunks overlapping the end continue it, a gap stops
+        assert_eq!(promote_chunks(&mut digits, &mut pending), 5);
+        assert_eq!(digits, "31415926");
+        assert_eq!(pending, chunks(&[(9, "35")]));
+
+        // nothing past the end, nothing added
+        let mut pending = chunks(&[(1, "14")]);
+        assert_eq!(promote_chunks(&mut digits, &mut pending), 0);
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn read() {
+        let path = std::env::temp_dir().join(format!("pi-search-journal-{}", std::process::id()));
+        Journal::remove(&path).unwrap();
+        assert!(Journal::read(&path).unwrap().is_empty());
+
+        let mut journal = Journal::open(&path).unwrap();
+        journal.record(0, "314").unwrap();
+        journal.record(3, "15").unwrap();
+        journal.record(0, "31").unwrap(); // shorter, the longer chunk stays
+        drop(journal);
+        // junk, and a line a crash cut short
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"x 12\n5 1a\n7\n9 26").unwrap();
+
+        assert_eq!(Journal::read(&path).unwrap(), chunks(&[(0, "314"), (3, "15")]));
+        Journal::remove(&path).unwrap();
+        Journal::remove(&path).unwrap();
+    }
+}
@@ -2,8 +2,36 @@
 #![cfg_attr(not(debug_assertions), deny(warnings))] // Forbid warnings in release builds
 #![warn(clippy::all, rust_2018_idioms)]
 
+pub mod analysis;
+pub mod api;
 mod app;
-mod search;
+pub mod async_search;
+pub mod cache_format;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod caches;
+pub mod config;
+pub mod curiosities;
+pub mod estimate;
+#[cfg(not(target_arch = "wasm32"))]
+mod file_dialog;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod file_search;
+pub mod history;
+pub mod import;
+pub mod job;
+pub mod jobs;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod journal;
+pub mod limits;
+pub mod plots;
+pub mod position;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod search;
+pub mod structures;
+#[cfg(target_arch = "wasm32")]
+pub mod web;
+pub mod windows;
+mod viewer;
 pub use app::TemplateApp;
 
 // ----------------------------------------------------------------------------
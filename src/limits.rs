@@ -0,0 +1,181 @@
+use futures::{channel::mpsc::{self, UnboundedReceiver, UnboundedSender}, StreamExt};
+use instant::Instant;
+use std::{sync::Mutex, time::Duration};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{sync::Condvar, thread};
+
+use crate::config::SearchConfig;
+
+// Time between request starts, `SearchConfig::validate` rejects rates too small for a Duration
+fn interval(requests_per_second: Option<f64>) -> Option<Duration> {
+    requests_per_second.filter(|&rate| rate > 0f64).and_then(|rate| Duration::try_from_secs_f64(1f64 / rate).ok())
+}
+
+// Books the next free start time, returns how long to wait for it
+fn book_start(next_start: &Mutex<Instant>, interval: Duration) -> Duration {
+    let mut next_start = next_start.lock().unwrap();
+    let start = (*next_start).max(Instant::now());
+    *next_start = start + interval;
+    start.saturating_duration_since(Instant::now())
+}
+
+// Caps the requests of all jobs sharing a client: at most `max_in_flight` at the same time,
+// started no faster than `requests_per_second`.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct RequestLimiter {
+    max_in_flight: usize,
+    interval: Option<Duration>,
+    in_flight: Mutex<usize>,
+    released: Condvar,
+    next_start: Mutex<Instant>,
+}
+
+// Held while a request runs
+#[cfg(not(target_arch = "wasm32"))]
+pub struct RequestPermit<'a> {
+    limiter: &'a RequestLimiter,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl RequestLimiter {
+    pub fn new(max_in_flight: usize, requests_per_second: Option<f64>) -> Self {
+        Self {
+            max_in_flight: max_in_flight.max(1),
+            interval: interval(requests_per_second),
+            in_flight: Mutex::new(0usize),
+            released: Condvar::new(),
+            next_start: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub fn from_config(config: &SearchConfig) -> Self {
+        Self::new(config.max_in_flight, config.requests_per_second)
+    }
+
+    // Blocks until the request may start
+    pub fn acquire(&self) -> RequestPermit<'_> {
+        {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            while *in_flight >= self.max_in_flight {
+                in_flight = self.released.wait(in_flight).unwrap();
+            }
+            *in_flight += 1;
+        }
+        if let Some(interval) = self.interval {
+            // every request books the next free slot, then sleeps until it comes
+            thread::sleep(book_start(&self.next_start, interval));
+        }
+        RequestPermit { limiter: self }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for RequestPermit<'_> {
+    fn drop(&mut self) {
+        *self.limiter.in_flight.lock().unwrap() -= 1;
+        self.limiter.released.notify_one();
+    }
+}
+
+// Async counterpart of `RequestLimiter` for `AsyncSearch`, waits without blocking the thread.
+// Free slots are tokens in a channel, waiting requests take them in turn.
+pub struct AsyncRequestLimiter {
+    interval: Option<Duration>,
+    slots: futures::lock::Mutex<UnboundedReceiver<()>>,
+    released: UnboundedSender<()>,
+    next_start: Mutex<Instant>,
+}
+
+pub struct AsyncRequestPermit<'a> {
+    limiter: &'a AsyncRequestLimiter,
+}
+
+impl AsyncRequestLimiter {
+    pub fn new(max_in_flight: usize, requests_per_second: Option<f64>) -> Self {
+        let (released, slots) = mpsc::unbounded();
+        for _ in 0..max_in_flight.max(1) {
+            let _ = released.unbounded_send(());
+        }
+        Self {
+            interval: interval(requests_per_second),
+            slots: futures::lock::Mutex::new(slots),
+            released,
+            next_start: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub fn from_config(config: &SearchConfig) -> Self {
+        Self::new(config.max_in_flight, config.requests_per_second)
+    }
+
+    // Resolves when the request may start
+    pub async fn acquire(&self) -> AsyncRequestPermit<'_> {
+        // never None, the limiter holds the sender
+        self.slots.lock().await.next().await;
+        let permit = AsyncRequestPermit { limiter: self };
+        if let Some(interval) = self.interval {
+            let wait = book_start(&self.next_start, interval);
+            if !wait.is_zero() {
+                sleep(wait).await;
+            }
+        }
+        permit
+    }
+}
+
+impl Drop for AsyncRequestPermit<'_> {
+    fn drop(&mut self) {
+        let _ = self.limiter.released.unbounded_send(());
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn sleep(duration: Duration) {
+    let (tx, rx) = futures::channel::oneshot::channel();
+    thread::spawn(move || {
+        thread::sleep(duration);
+        let _ = tx.send(());
+    });
+    let _ = rx.await;
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn sleep(duration: Duration) {
+    use eframe::wasm_bindgen::JsValue;
+    let millis = duration.as_millis().min(i32::MAX as u128) as i32;
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        let scheduled = web_sys::window().is_some_and(|window| {
+            window.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, millis).is_ok()
+        });
+        if !scheduled {
+            let _ = resolve.call0(&JsValue::NULL);
+        }
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+
+    use super::*;
+
+    #[test]
+    fn limiter() {
+        let limiter = AsyncRequestLimiter::new(2, None);
+        let first = limiter.acquire().now_or_never().unwrap();
+        let _second = limiter.acquire().now_or_never().unwrap();
+        assert!(limiter.acquire().now_or_never().is_none());
+        drop(first);
+        assert!(limiter.acquire().now_or_never().is_some());
+
+        let limiter = AsyncRequestLimiter::new(1, Some(20f64));
+        let started = Instant::now();
+        futures::executor::block_on(async {
+            for _ in 0..3 {
+                limiter.acquire().await;
+            }
+        });
+        assert!(started.elapsed() >= Duration::from_millis(100));
+    }
+}
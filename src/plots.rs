@@ -0,0 +1,466 @@
+use eframe::egui::{self, epaint::text::Fonts, Align2, Color32, Pos2, Sense, Stroke, TextStyle, Ui, Vec2};
+use std::fmt::Write;
+
+use crate::cache_format::crc32;
+
+// Views of the digit stream. Each view is built as a `Figure` in pixel units, which is
+// painted in the window and written out as SVG or PNG, so the exports match the screen.
+
+const DIGIT_COLORS: [[u8; 3]; 10] = [
+    [31, 119, 180], [255, 127, 14], [44, 160, 44], [214, 39, 40], [148, 103, 189],
+    [140, 86, 75], [227, 119, 194], [127, 127, 127], [188, 189, 34], [23, 190, 207],
+];
+const AXIS_COLOR: [u8; 3] = [60, 60, 60];
+const MARGIN: f32 = 20f32;
+const MAX_GAP_BARS: usize = 2000;
+pub const MAX_GRID_CELLS: usize = 100_000; // each is a shape painted every frame
+
+pub enum Primitive {
+    Rect { x: f32, y: f32, w: f32, h: f32, color: [u8; 3] },
+    Line { points: Vec<(f32, f32)>, color: [u8; 3] },
+    Text { x: f32, y: f32, text: String }, // centered
+}
+
+pub struct Figure {
+    pub width: f32,
+    pub height: f32,
+    pub shapes: Vec<Primitive>,
+}
+
+pub fn digit_counts(digits: &str) -> [usize; 10] {
+    let mut counts = [0usize; 10];
+    for b in digits.bytes() {
+        counts[(b - b'0') as usize] += 1;
+    }
+    counts
+}
+
+// Each digit is a unit step in direction digit * 36 degrees
+pub fn random_walk(digits: &str) -> Vec<(f32, f32)> {
+    let mut points = Vec::with_capacity(digits.len() + 1);
+    let (mut x, mut y) = (0f32, 0f32);
+    points.push((x, y));
+    for b in digits.bytes() {
+        let angle = (b - b'0') as f32 * std::f32::consts::PI / 5f32;
+        x += angle.cos();
+        y -= angle.sin();
+        points.push((x, y));
+    }
+    points
+}
+
+// Distances between consecutive (possibly overlapping) occurrences of the pattern
+pub fn pattern_gaps(digits: &str, pattern: &str) -> Vec<usize> {
+    let mut gaps = Vec::new();
+    let mut last = None;
+    let mut from = 0;
+    while let Some(i) = digits[from..].find(pattern) {
+        let position = from + i;
+        if let Some(last) = last {
+            gaps.push(position - last);
+        }
+        last = Some(position);
+        from = position + 1;
+    }
+    gaps
+}
+
+pub fn histogram_figure(counts: &[usize; 10]) -> Figure {
+    let (width, height) = (440f32, 300f32);
+    let max = counts.iter().copied().max().unwrap_or(0).max(1) as f32;
+    let bar_width = (width - 2f32 * MARGIN) / 10f32;
+    let plot_height = height - 3f32 * MARGIN;
+
+    let mut shapes = Vec::new();
+    for (digit, &count) in counts.iter().enumerate() {
+        let h = plot_height * count as f32 / max;
+        let x = MARGIN + digit as f32 * bar_width;
+        let bottom = height - 2f32 * MARGIN;
+        shapes.push(Primitive::Rect { x: x + 2f32, y: bottom - h, w: bar_width - 4f32, h, color: DIGIT_COLORS[digit] });
+        shapes.push(Primitive::Text { x: x + bar_width / 2f32, y: bottom + MARGIN / 2f32, text: digit.to_string() });
+        shapes.push(Primitive::Text { x: x + bar_width / 2f32, y: bottom - h - MARGIN / 2f32, text: count.to_string() });
+    }
+    shapes.push(Primitive::Line { points: vec![(MARGIN, height - 2f32 * MARGIN), (width - MARGIN, height - 2f32 * MARGIN)], color: AXIS_COLOR });
+    Figure { width, height, shapes }
+}
+
+pub fn random_walk_figure(points: &[(f32, f32)]) -> Figure {
+    let size = 400f32;
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (0f32, 0f32, 0f32, 0f32);
+    for &(x, y) in points {
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x);
+        max_y = max_y.max(y);
+    }
+    let scale = (size - 2f32 * MARGIN) / (max_x - min_x).max(max_y - min_y).max(1f32);
+    let project = |&(x, y): &(f32, f32)| (MARGIN + (x - min_x) * scale, MARGIN + (y - min_y) * scale);
+
+    let mut shapes = vec![Primitive::Line { points: points.iter().map(project).collect(), color: DIGIT_COLORS[0] }];
+    // start in green, end in red
+    if let (Some(first), Some(last)) = (points.first(), points.last()) {
+        for (point, color) in [(first, DIGIT_COLORS[2]), (last, DIGIT_COLORS[3])] {
+            let (x, y) = project(point);
+            shapes.push(Primitive::Rect { x: x - 3f32, y: y - 3f32, w: 6f32, h: 6f32, color });
+        }
+    }
+    Figure { width: size, height: size, shapes }
+}
+
+// Only the first MAX_GRID_CELLS digits
+pub fn color_grid_figure(digits: &str, columns: usize, cell: f32) -> Figure {
+    let digits = &digits[..digits.len().min(MAX_GRID_CELLS)];
+    let rows = digits.len().div_ceil(columns).max(1);
+    let shapes = digits.bytes().enumerate().map(|(i, b)| Primitive::Rect {
+        x: (i % columns) as f32 * cell,
+        y: (i / columns) as f32 * cell,
+        w: cell,
+        h: cell,
+        color: DIGIT_COLORS[(b - b'0') as usize],
+    }).collect();
+    Figure { width: columns as f32 * cell, height: rows as f32 * cell, shapes }
+}
+
+pub fn gap_figure(pattern: &str, gaps: &[usize]) -> Figure {
+    let (width, height) = (600f32, 300f32);
+    let mut shapes = Vec::new();
+    let text = match gaps.len() {
+        0 => format!("Fewer than two occurrences of {pattern}"),
+        n => format!("{n} gaps between occurrences of {pattern}, mean {:.1}", gaps.iter().sum::<usize>() as f64 / n as f64),
+    };
+    shapes.push(Primitive::Text { x: width / 2f32, y: MARGIN / 2f32, text });
+
+    let gaps = &gaps[..gaps.len().min(MAX_GAP_BARS)];
+    let max = gaps.iter().copied().max().unwrap_or(0).max(1) as f32;
+    let bar_width = (width - 2f32 * MARGIN) / gaps.len().max(1) as f32;
+    let plot_height = height - 3f32 * MARGIN;
+    let bottom = height - MARGIN;
+    for (i, &gap) in gaps.iter().enumerate() {
+        let h = plot_height * gap as f32 / max;
+        shapes.push(Primitive::Rect { x: MARGIN + i as f32 * bar_width, y: bottom - h, w: bar_width.max(1f32), h, color: DIGIT_COLORS[0] });
+    }
+    shapes.push(Primitive::Line { points: vec![(MARGIN, bottom), (width - MARGIN, bottom)], color: AXIS_COLOR });
+    Figure { width, height, shapes }
+}
+
+fn rgb(color: [u8; 3]) -> String {
+    format!("rgb({},{},{})", color[0], color[1], color[2])
+}
+
+pub fn to_svg(figure: &Figure) -> String {
+    let mut svg = String::new();
+    writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#, figure.width, figure.height, figure.width, figure.height).unwrap();
+    writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#).unwrap();
+    for shape in &figure.shapes {
+        match shape {
+            Primitive::Rect { x, y, w, h, color } => {
+                writeln!(svg, r#"<rect x="{x}" y="{y}" width="{w}" height="{h}" fill="{}"/>"#, rgb(*color)).unwrap();
+            },
+            Primitive::Line { points, color } => {
+                svg.push_str(r#"<polyline fill="none" stroke-width="1" stroke=""#);
+                svg.push_str(rgb(*color).as_str());
+                svg.push_str(r#"" points=""#);
+                for (x, y) in points {
+                    write!(svg, "{x:.2},{y:.2} ").unwrap();
+                }
+                svg.push_str("\"/>\n");
+            },
+            Primitive::Text { x, y, text } => {
+                writeln!(svg, r#"<text x="{x}" y="{y}" text-anchor="middle" dominant-baseline="middle" font-family="sans-serif" font-size="12">{text}</text>"#).unwrap();
+            },
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// RGB pixels, white to start with
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Self { width, height, pixels: vec![255u8; width * height * 3] }
+    }
+
+    // `alpha` of 255 replaces the pixel
+    fn blend(&mut self, x: isize, y: isize, color: [u8; 3], alpha: u8) {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            let i = (y as usize * self.width + x as usize) * 3;
+            for (pixel, c) in self.pixels[i..i + 3].iter_mut().zip(color) {
+                *pixel = ((*pixel as u32 * (255 - alpha as u32) + c as u32 * alpha as u32) / 255) as u8;
+            }
+        }
+    }
+
+    fn plot(&mut self, x: isize, y: isize, color: [u8; 3]) {
+        self.blend(x, y, color, 255);
+    }
+}
+
+// Text is drawn with the glyphs of `fonts`, the ones the window uses
+pub fn to_png(figure: &Figure, fonts: &Fonts) -> Vec<u8> {
+    let mut canvas = Canvas::new(figure.width.ceil() as usize, figure.height.ceil() as usize);
+    for shape in &figure.shapes {
+        match shape {
+            Primitive::Rect { x, y, w, h, color } => {
+                for py in y.round() as isize..(y + h).round() as isize {
+                    for px in x.round() as isize..(x + w).round() as isize {
+                        canvas.plot(px, py, *color);
+                    }
+                }
+            },
+            Primitive::Line { points, color } => {
+                for segment in points.windows(2) {
+                    let ((x0, y0), (x1, y1)) = (segment[0], segment[1]);
+                    let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1f32);
+                    for step in 0..=steps as usize {
+                        let t = step as f32 / steps;
+                        canvas.plot((x0 + (x1 - x0) * t).round() as isize, (y0 + (y1 - y0) * t).round() as isize, *color);
+                    }
+                }
+            },
+            Primitive::Text { x, y, text } => draw_text(&mut canvas, fonts, Pos2::new(*x, *y), text),
+        }
+    }
+    encode_png(canvas.width, canvas.height, &canvas.pixels)
+}
+
+// Centered like `paint` does it, each glyph is scaled from the font texture to one pixel per point
+fn draw_text(canvas: &mut Canvas, fonts: &Fonts, center: Pos2, text: &str) {
+    let galley = fonts.layout_no_wrap(text.to_string(), TextStyle::Small, Color32::BLACK);
+    // laying out may add glyphs to the texture, so it's fetched afterwards
+    let image = fonts.font_image();
+    let origin = center - galley.size() / 2f32;
+    for glyph in galley.rows.iter().flat_map(|row| row.glyphs.iter()) {
+        let uv = glyph.uv_rect;
+        if uv.is_nothing() {
+            continue;
+        }
+        let min = origin + glyph.pos.to_vec2() + uv.offset;
+        let (texels_x, texels_y) = ((uv.max[0] - uv.min[0]) as f32, (uv.max[1] - uv.min[1]) as f32);
+        for py in 0..uv.size.y.round() as usize {
+            for px in 0..uv.size.x.round() as usize {
+                let tx = uv.min[0] as usize + ((px as f32 + 0.5) * texels_x / uv.size.x) as usize;
+                let ty = uv.min[1] as usize + ((py as f32 + 0.5) * texels_y / uv.size.y) as usize;
+                let alpha = image.pixels[ty.min(image.height - 1) * image.width + tx.min(image.width - 1)];
+                canvas.blend(min.x.round() as isize + px as isize, min.y.round() as isize + py as isize, [0u8; 3], alpha);
+            }
+        }
+    }
+}
+
+// 8-bit RGB, no filtering
+fn encode_png(width: usize, height: usize, pixels: &[u8]) -> Vec<u8> {
+    let mut raw = Vec::with_capacity((width * 3 + 1) * height);
+    for row in pixels.chunks(width * 3) {
+        raw.push(0u8);
+        raw.extend_from_slice(row);
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &miniz_oxide::deflate::compress_to_vec_zlib(&raw, 6));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[png.len() - data.len() - 4..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PlotKind {
+    Histogram,
+    RandomWalk,
+    ColorGrid,
+    Gaps,
+}
+
+impl PlotKind {
+    pub const ALL: [PlotKind; 4] = [PlotKind::Histogram, PlotKind::RandomWalk, PlotKind::ColorGrid, PlotKind::Gaps];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PlotKind::Histogram => "Digit frequencies",
+            PlotKind::RandomWalk => "Random walk",
+            PlotKind::ColorGrid => "Color grid",
+            PlotKind::Gaps => "Pattern gaps",
+        }
+    }
+
+    fn file_stem(self) -> &'static str {
+        match self {
+            PlotKind::Histogram => "histogram",
+            PlotKind::RandomWalk => "random_walk",
+            PlotKind::ColorGrid => "color_grid",
+            PlotKind::Gaps => "gaps",
+        }
+    }
+}
+
+pub struct PlotExport {
+    pub file_name: String,
+    pub bytes: Vec<u8>,
+    pub mime_type: &'static str,
+}
+
+// Visualization window; figures are rebuilt on request rather than every frame
+pub struct PlotPanel {
+    kind: PlotKind,
+    digit_count: usize,
+    columns: usize,
+    pattern: String,
+    figure: Option<Figure>,
+}
+
+impl Default for PlotPanel {
+    fn default() -> Self {
+        Self {
+            kind: PlotKind::Histogram,
+            digit_count: 10_000,
+            columns: 100,
+            pattern: "14".to_string(),
+            figure: None,
+        }
+    }
+}
+
+impl PlotPanel {
+    pub fn show(&mut self, ui: &mut Ui, digits: &str) -> Option<PlotExport> {
+        let mut redraw = false;
+        egui::ComboBox::from_label("View")
+            .selected_text(self.kind.name())
+            .show_ui(ui, |ui| {
+                for kind in PlotKind::ALL {
+                    redraw |= ui.selectable_value(&mut self.kind, kind, kind.name()).clicked();
+                }
+            });
+
+        ui.horizontal(|ui| {
+            ui.label("Digits: ");
+            ui.add(egui::DragValue::new(&mut self.digit_count).clamp_range(1usize..=digits.len().max(1)).speed(100));
+            match self.kind {
+                PlotKind::ColorGrid => {
+                    ui.label("Columns: ");
+                    ui.add(egui::DragValue::new(&mut self.columns).clamp_range(1usize..=1000usize));
+                },
+                PlotKind::Gaps => {
+                    ui.label("Pattern: ");
+                    ui.add(egui::TextEdit::singleline(&mut self.pattern).desired_width(80f32));
+                },
+                _ => {},
+            }
+            redraw |= ui.button("Draw").clicked();
+        });
+
+        if redraw || self.figure.is_none() {
+            self.figure = self.build(&digits[..self.digit_count.min(digits.len())]);
+        }
+        let figure = self.figure.as_ref()?;
+        if self.kind == PlotKind::ColorGrid && self.digit_count.min(digits.len()) > MAX_GRID_CELLS {
+            ui.label(format!("Only the first {MAX_GRID_CELLS} digits are drawn"));
+        }
+
+        let mut export = None;
+        ui.horizontal(|ui| {
+            if ui.button("Export PNG").clicked() {
+                export = Some(PlotExport { file_name: format!("{}.png", self.kind.file_stem()), bytes: to_png(figure, ui.fonts()), mime_type: "image/png" });
+            }
+            if ui.button("Export SVG").clicked() {
+                export = Some(PlotExport { file_name: format!("{}.svg", self.kind.file_stem()), bytes: to_svg(figure).into_bytes(), mime_type: "image/svg+xml" });
+            }
+        });
+
+        egui::ScrollArea::both().show(ui, |ui| paint(ui, figure));
+        export
+    }
+
+    fn build(&self, digits: &str) -> Option<Figure> {
+        if digits.is_empty() {
+            return None;
+        }
+        Some(match self.kind {
+            PlotKind::Histogram => histogram_figure(&digit_counts(digits)),
+            PlotKind::RandomWalk => random_walk_figure(&random_walk(digits)),
+            PlotKind::ColorGrid => color_grid_figure(digits, self.columns, 4f32),
+            PlotKind::Gaps => {
+                if self.pattern.is_empty() || !self.pattern.chars().all(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+                gap_figure(self.pattern.as_str(), &pattern_gaps(digits, self.pattern.as_str()))
+            },
+        })
+    }
+}
+
+fn color32(color: [u8; 3]) -> Color32 {
+    Color32::from_rgb(color[0], color[1], color[2])
+}
+
+fn paint(ui: &mut Ui, figure: &Figure) {
+    let (response, painter) = ui.allocate_painter(Vec2::new(figure.width, figure.height), Sense::hover());
+    let origin = response.rect.min;
+    let at = |x: f32, y: f32| Pos2::new(origin.x + x, origin.y + y);
+
+    painter.rect_filled(response.rect, 0f32, Color32::WHITE);
+    for shape in &figure.shapes {
+        match shape {
+            Primitive::Rect { x, y, w, h, color } => {
+                painter.rect_filled(egui::Rect::from_min_size(at(*x, *y), Vec2::new(*w, *h)), 0f32, color32(*color));
+            },
+            Primitive::Line { points, color } => {
+                painter.add(egui::Shape::line(points.iter().map(|&(x, y)| at(x, y)).collect(), Stroke::new(1f32, color32(*color))));
+            },
+            Primitive::Text { x, y, text } => {
+                painter.text(at(*x, *y), Align2::CENTER_CENTER, text, TextStyle::Small, Color32::BLACK);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eframe::egui::epaint::text::FontDefinitions;
+
+    // The RGB pixels of a PNG written by `encode_png`, filter bytes dropped
+    fn decode_png(png: &[u8]) -> Vec<u8> {
+        let len = u32::from_be_bytes(png[33..37].try_into().unwrap()) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let width = u32::from_be_bytes(png[16..20].try_into().unwrap()) as usize;
+        let raw = miniz_oxide::inflate::decompress_to_vec_zlib(&png[41..41 + len]).unwrap();
+        raw.chunks(width * 3 + 1).flat_map(|row| row[1..].to_vec()).collect()
+    }
+
+    #[test]
+    fn png_text() {
+        let fonts = Fonts::new(1f32, FontDefinitions::default());
+        let blank = Figure { width: 40f32, height: 20f32, shapes: Vec::new() };
+        assert!(decode_png(&to_png(&blank, &fonts)).iter().all(|&p| p == 255));
+
+        let text = Figure { shapes: vec![Primitive::Text { x: 20f32, y: 10f32, text: "314".to_string() }], ..blank };
+        assert!(decode_png(&to_png(&text, &fonts)).iter().any(|&p| p < 128));
+    }
+
+    #[test]
+    fn color_grid_cap() {
+        let digits = "1".repeat(MAX_GRID_CELLS + 10);
+        let figure = color_grid_figure(&digits, 100, 1f32);
+        assert_eq!(figure.shapes.len(), MAX_GRID_CELLS);
+        assert_eq!(figure.height, (MAX_GRID_CELLS / 100) as f32);
+        assert_eq!(color_grid_figure("123", 100, 4f32).shapes.len(), 3);
+    }
+}
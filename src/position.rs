@@ -0,0 +1,69 @@
+// The digit cache is a plain string starting with the integer part: "314159...".
+// Offsets into it are what the search jobs report, a `PositionConvention` turns them into
+// the positions people expect and back.
+
+pub const PI_INTEGER_DIGITS: usize = 1; // the "3"
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+pub enum PositionConvention {
+    CacheOffset,         // 0-based offset into the cache, the leading "3" is 0
+    #[default]
+    AfterPointOneBased,  // the first digit after the decimal point is 1 (Pi-Search Page, most tables)
+    AfterPointZeroBased, // the first digit after the decimal point is 0
+}
+
+impl PositionConvention {
+    pub const ALL: [PositionConvention; 3] = [
+        PositionConvention::AfterPointOneBased,
+        PositionConvention::AfterPointZeroBased,
+        PositionConvention::CacheOffset,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PositionConvention::CacheOffset => "Cache offset (0 = leading 3)",
+            PositionConvention::AfterPointOneBased => "After decimal point, 1-based",
+            PositionConvention::AfterPointZeroBased => "After decimal point, 0-based",
+        }
+    }
+
+    // Digits of the integer part get positions <= 0 in the after-point conventions
+    pub fn to_position(self, offset: usize, integer_digits: usize) -> i64 {
+        match self {
+            PositionConvention::CacheOffset => offset as i64,
+            PositionConvention::AfterPointOneBased => offset as i64 - integer_digits as i64 + 1,
+            PositionConvention::AfterPointZeroBased => offset as i64 - integer_digits as i64,
+        }
+    }
+
+    pub fn to_offset(self, position: i64, integer_digits: usize) -> Option<usize> {
+        let offset = match self {
+            PositionConvention::CacheOffset => position,
+            PositionConvention::AfterPointOneBased => position + integer_digits as i64 - 1,
+            PositionConvention::AfterPointZeroBased => position + integer_digits as i64,
+        };
+        usize::try_from(offset).ok()
+    }
+
+    pub fn format(self, offset: usize, integer_digits: usize) -> String {
+        self.to_position(offset, integer_digits).to_string()
+    }
+
+    pub fn parse(self, text: &str, integer_digits: usize) -> Result<usize, String> {
+        let position: i64 = text.trim().parse().map_err(|_| format!("\"{}\" is not a position", text.trim()))?;
+        self.to_offset(position, integer_digits).ok_or_else(|| format!("Position {position} is before the first digit"))
+    }
+}
+
+// Cache files store the decimal point explicitly: "3.14159..."
+pub fn format_cache(digits: &str, integer_digits: usize) -> String {
+    if digits.len() <= integer_digits {
+        return digits.to_string();
+    }
+    let mut text = String::with_capacity(digits.len() + 1);
+    text.push_str(&digits[..integer_digits]);
+    text.push('.');
+    text.push_str(&digits[integer_digits..]);
+    text
+}
@@ -1,5 +1,9 @@
 use reqwest::blocking::{Client, Response};
-use std::{sync::{mpsc::{self, Receiver}, Mutex, Arc}, thread};
+use std::{collections::BTreeMap, io, ops::Range, path::PathBuf, sync::{mpsc::{self, Receiver, Sender}, Condvar, Mutex, Arc}, thread, time::Instant};
+
+use crate::{analysis::*, api::*, config::{SearchConfig, MAX_WORKERS}, estimate::*, job::*, journal::*, limits::RequestLimiter, position::PI_INTEGER_DIGITS};
+
+const SCAN_DIGITS: usize = 1 << 20; // cached digits searched per lock of saved_digits
 
 // Arc<Mutex<T>> -> MutexGuard<T>
 macro_rules! unwrap_am {
@@ -8,21 +12,18 @@ macro_rules! unwrap_am {
     };
 }
 
-fn create_client() -> Client {
-    Client::builder().danger_accept_invalid_certs(true).build().unwrap()
-}
-
-pub const MAX_DIGITS_PER_REQUEST: usize = 1000;
-
 pub struct Search {
+    config: SearchConfig,
     client: Arc<Mutex<Client>>,
+    limiter: Arc<RequestLimiter>, // shared by all jobs, replaced with the config
 
     saved_digits: Arc<Mutex<String>>,
+    integer_digits: usize, // digits of saved_digits before the decimal point
+    pending_chunks: Arc<Mutex<BTreeMap<usize, String>>>, // downloaded, but not contiguous with saved_digits yet
+    claims: Arc<Claims>, // being downloaded by a preload or search
+    journal_path: Option<PathBuf>,
 
-    preload_thread_handler: Option<thread::JoinHandle<()>>,
-    search_thread_handler: Option<thread::JoinHandle<()>>,
-
-    digits_per_request: usize, // must be <= MAX_DIGITS_PER_REQUEST
+    jobs: Vec<(SearchState, thread::JoinHandle<()>)>, // any number of preloads and searches share the cache
 }
 
 
@@ -34,13 +35,150 @@ fn send_request(client: &Client, url: &str, query: Option<&[(&str, &str)]>) -> R
     Ok(req.send()?)
 }
 
-fn get_digits(client: &Client, start: usize, number_of_digits: usize) -> Result<String, Box<dyn std::error::Error>> {
-    let text = send_request(&client, format!("https://api.pi.delivery/v1/pi?start={start}&numberOfDigits={number_of_digits}").as_str(), None)?.text()?;
-    let digits = &text[text.find(':').unwrap() + 2 .. text.len() - 2];
-    Ok(digits.to_string())
+// returns digits and the size of the response body
+fn get_digits(client: &Client, config: &SearchConfig, start: usize, number_of_digits: usize) -> Result<(String, usize), Box<dyn std::error::Error>> {
+    let response = send_request(client, config.digits_url(start, number_of_digits).as_str(), None)?;
+    let status = response.status().as_u16();
+    let text = response.text()?;
+    Ok((parse_digits(status, text.as_str(), number_of_digits)?, text.len()))
+}
+
+// get_digits with retries, every failed attempt is reported as a warning
+fn fetch_digits(client: &Mutex<Client>, limiter: &RequestLimiter, config: &SearchConfig, start: usize, number_of_digits: usize, events: &Sender<JobEvent>) -> Result<(String, usize), String> {
+    // the client is a handle to a shared pool, holding the lock would allow one request at a time
+    let client = client.lock().unwrap().clone();
+    let mut attempt = 1;
+    loop {
+        let permit = limiter.acquire();
+        let res = get_digits(&client, config, start, number_of_digits);
+        drop(permit);
+        match res {
+            Ok(res) => return Ok(res),
+            Err(err) if attempt < MAX_REQUEST_ATTEMPTS => {
+                let _ = events.send(JobEvent::Warning(format!("Request for digits {start}-{} failed (attempt {attempt}): {err}", start + number_of_digits)));
+                attempt += 1;
+            },
+            Err(err) => return Err(format!("Request for digits {start}-{} failed: {err}", start + number_of_digits)),
+        }
+    }
+}
+
+// Stores a downloaded chunk, it joins saved_digits as soon as the digits before it are there
+fn store_chunk(digits: &Mutex<String>, pending: &Mutex<BTreeMap<usize, String>>, start: usize, chunk: String) {
+    let mut digits = digits.lock().unwrap();
+    let mut pending = pending.lock().unwrap();
+    pending.insert(start, chunk);
+    promote_chunks(&mut digits, &mut pending);
+}
+
+// Up to `max` loaded digits from `pos` on, from the cache or a chunk that doesn't continue it yet
+fn read_loaded(digits: &Mutex<String>, pending: &Mutex<BTreeMap<usize, String>>, pos: usize, max: usize) -> String {
+    {
+        let digits = digits.lock().unwrap();
+        if pos < digits.len() {
+            return digits[pos..pos.saturating_add(max).min(digits.len())].to_string();
+        }
+    }
+    let pending = pending.lock().unwrap();
+    match pending.range(..=pos).rev().find(|(&start, chunk)| pos < start + chunk.len()) {
+        Some((&start, chunk)) => chunk[pos - start..(pos - start).saturating_add(max).min(chunk.len())].to_string(),
+        None => String::new(),
+    }
+}
+
+// What a job thread reads the digits through
+#[derive(Clone)]
+struct DigitSource {
+    digits: Arc<Mutex<String>>,
+    pending: Arc<Mutex<BTreeMap<usize, String>>>,
+    claims: Arc<Claims>,
+    client: Arc<Mutex<Client>>,
+    limiter: Arc<RequestLimiter>,
+    config: SearchConfig,
+}
+
+impl DigitSource {
+    // Up to `max` digits from `pos` on. Missing digits are fetched first, or waited for if another
+    // job is fetching them already.
+    fn read(&self, pos: usize, max: usize, meter: &mut ProgressMeter, events: &Sender<JobEvent>) -> Result<String, String> {
+        loop {
+            let chunk = read_loaded(&self.digits, &self.pending, pos, max);
+            if !chunk.is_empty() {
+                return Ok(chunk);
+            }
+            let end = pos.saturating_add(self.config.digits_per_request.min(max));
+            for claim in self.claims.claim(pos..end, &self.digits, &self.pending) {
+                let (new_digits, bytes) = fetch_digits(&self.client, &self.limiter, &self.config, claim.range.start, claim.range.len(), events)?;
+                meter.add_bytes(bytes);
+                store_chunk(&self.digits, &self.pending, claim.range.start, new_digits);
+            }
+        }
+    }
+}
+
+// Digit ranges being downloaded right now. A preload and a search run side by side and both
+// claim what they fetch, so no digit is requested twice.
+#[derive(Default)]
+struct Claims {
+    ranges: Mutex<Vec<Range<usize>>>,
+    changed: Condvar,
+}
+
+// Released when dropped, after the digits were stored or the request failed
+struct Claim<'a> {
+    claims: &'a Claims,
+    range: Range<usize>,
+}
+
+impl Claims {
+    // Claims the parts of `range` that are neither loaded nor claimed by another job. If other jobs
+    // are downloading all of them, waits for those; an empty result means the whole range is loaded.
+    fn claim(&self, range: Range<usize>, digits: &Mutex<String>, pending: &Mutex<BTreeMap<usize, String>>) -> Vec<Claim<'_>> {
+        let mut claimed = self.ranges.lock().unwrap();
+        loop {
+            // pending first: a chunk promoted in between shows up in digits
+            let mut loaded = completed_ranges(&pending.lock().unwrap());
+            loaded.insert(0, 0..digits.lock().unwrap().len());
+            let missing = missing_ranges(range.clone(), &loaded);
+            if missing.is_empty() {
+                return Vec::new();
+            }
+
+            let mut busy = claimed.clone();
+            busy.sort_by_key(|r| r.start);
+            let free: Vec<Range<usize>> = missing.into_iter().flat_map(|m| missing_ranges(m, &busy)).collect();
+            if !free.is_empty() {
+                claimed.extend(free.iter().cloned());
+                return free.into_iter().map(|range| Claim { claims: self, range }).collect();
+            }
+            claimed = self.changed.wait(claimed).unwrap();
+        }
+    }
+}
+
+impl Drop for Claim<'_> {
+    fn drop(&mut self) {
+        let mut claimed = self.claims.ranges.lock().unwrap();
+        if let Some(i) = claimed.iter().position(|r| *r == self.range) {
+            claimed.swap_remove(i);
+        }
+        self.claims.changed.notify_all();
+    }
 }
 
-#[derive(PartialEq)]
+enum WorkerMessage {
+    Loaded { start: usize, digits: String, bytes: usize },
+    Covered(usize), // digits of a request that another job downloaded
+    Failed(String),
+}
+
+impl Default for Search {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum SearchState {
     Idle,
     Preloading,
@@ -50,20 +188,42 @@ pub enum SearchState {
 #[allow(dead_code)]
 impl Search {
     pub fn new() -> Self {
-        Self {
-            client: Arc::new(Mutex::new(create_client())),
+        Self::with_config(SearchConfig::default()).unwrap()
+    }
+
+    pub fn with_config(config: SearchConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            client: Arc::new(Mutex::new(config.build_blocking_client()?)),
+            limiter: Arc::new(RequestLimiter::from_config(&config)),
+            config,
             saved_digits: Arc::default(),
-            preload_thread_handler: None,
-            search_thread_handler: None,
-            digits_per_request: MAX_DIGITS_PER_REQUEST,
-        }
+            integer_digits: PI_INTEGER_DIGITS,
+            pending_chunks: Arc::default(),
+            claims: Arc::default(),
+            journal_path: None,
+            jobs: Vec::new(),
+        })
     }
 
+    pub fn get_config(&self) -> &SearchConfig {
+        &self.config
+    }
+
+    // Applies to jobs started afterwards, running jobs keep their settings
+    pub fn set_config(&mut self, config: SearchConfig) -> Result<(), Box<dyn std::error::Error>> {
+        config.validate()?;
+        self.client = Arc::new(Mutex::new(config.build_blocking_client()?));
+        self.limiter = Arc::new(RequestLimiter::from_config(&config));
+        self.config = config;
+        Ok(())
+    }
+
+    // Preloading while a search runs too
     pub fn get_state(&self) -> SearchState {
-        if self.preload_thread_handler.is_some() {
+        if self.is_preloading() {
             SearchState::Preloading
         }
-        else if self.search_thread_handler.is_some() {
+        else if self.is_searching() {
             SearchState::Searching
         }
         else {
@@ -71,213 +231,390 @@ impl Search {
         }
     }
 
+    fn is_running(&self, state: SearchState) -> bool {
+        self.jobs.iter().any(|(s, handler)| *s == state && !handler.is_finished())
+    }
+
+    pub fn is_preloading(&self) -> bool {
+        self.is_running(SearchState::Preloading)
+    }
+    pub fn is_searching(&self) -> bool {
+        self.is_running(SearchState::Searching)
+    }
+
     pub fn get_digits(&self) -> Arc<Mutex<String>> {
         self.saved_digits.clone()
     }
     pub fn digits_loaded(&self) -> usize {
         unwrap_am!(self.saved_digits).len()
     }
+    pub fn integer_digits(&self) -> usize {
+        self.integer_digits
+    }
+
+    // Expected position of the pattern and the chances to find it in the cache or the bounds.
+    // `rate` is the download speed in digits/s, for the ETA.
+    pub fn estimate(&self, search_for: &str, bounds: &SearchBounds, rate: Option<f64>) -> SearchEstimate {
+        estimate_search(search_for, bounds, self.digits_loaded(), rate)
+    }
+
+    pub fn set_digits_per_request(&mut self, digits_per_request: usize) {
+        self.config.digits_per_request = digits_per_request.clamp(1usize, MAX_DIGITS_PER_REQUEST);
+    }
+
+    pub fn set_workers(&mut self, workers: usize) {
+        self.config.workers = workers.clamp(1usize, MAX_WORKERS);
+    }
 
-    pub fn preload(&mut self, count: usize, num_of_threads: usize) -> (Receiver<usize>, Receiver<()>) {
-        if self.get_state() != SearchState::Idle {
-            panic!("Can't preload: state must be idle");
+    pub fn set_journal(&mut self, path: Option<PathBuf>) {
+        self.journal_path = path;
+    }
+
+    // Forgets the loaded and pending digits, e.g. when switching to another cache file
+    pub fn clear_digits(&mut self) {
+        unwrap_am!(self.saved_digits).clear();
+        unwrap_am!(self.pending_chunks).clear();
+    }
+
+    // Digits downloaded by an interrupted preload that don't continue the cache yet
+    pub fn pending_digits(&self) -> usize {
+        unwrap_am!(self.pending_chunks).values().map(String::len).sum()
+    }
+
+    // Picks up the chunks recorded in the journal, the next preload won't download them again
+    pub fn resume_from_journal(&mut self) -> io::Result<usize> {
+        let path = match &self.journal_path {
+            Some(path) => path,
+            None => return Ok(0usize),
+        };
+        let chunks = Journal::read(path)?;
+        let restored = chunks.values().map(String::len).sum();
+
+        let mut digits = unwrap_am!(self.saved_digits);
+        let mut pending = unwrap_am!(self.pending_chunks);
+        for (start, chunk) in chunks {
+            if pending.get(&start).map_or(true, |c| c.len() < chunk.len()) {
+                pending.insert(start, chunk);
+            }
         }
-        if num_of_threads == 0 {
-            panic!("Can't preload: num_of_threads must be greater then 0");
+        promote_chunks(&mut digits, &mut pending);
+        Ok(restored)
+    }
+
+    // Call once the cache is stored safely elsewhere: rewrites the journal with only the pending chunks
+    pub fn clear_journal(&mut self) -> io::Result<()> {
+        let path = match &self.journal_path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        {
+            let mut digits = unwrap_am!(self.saved_digits);
+            let mut pending = unwrap_am!(self.pending_chunks);
+            promote_chunks(&mut digits, &mut pending);
         }
+        Journal::remove(path)?;
 
-        let (loa_tx, loa_rx) = mpsc::channel();
-        let (res_tx, res_rx) = mpsc::channel();
+        let pending = unwrap_am!(self.pending_chunks);
+        if !pending.is_empty() {
+            let mut journal = Journal::open(path)?;
+            for (start, chunk) in pending.iter() {
+                journal.record(*start, chunk)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Other preloads and searches can run at the same time, see `search`.
+    // Dropping the receiver stops the job.
+    pub fn preload(&mut self, count: usize) -> Receiver<JobEvent> {
+        let (ev_tx, ev_rx) = mpsc::channel();
 
         let c_digits = self.saved_digits.clone();
+        let c_pending = self.pending_chunks.clone();
+        let c_claims = self.claims.clone();
         let c_client = self.client.clone();
-        let digits_per_request = self.digits_per_request;
+        let c_limiter = self.limiter.clone();
+        let c_config = self.config.clone();
+        let (digits_per_request, num_of_threads) = (self.config.digits_per_request, self.config.workers);
+        let journal_path = self.journal_path.clone();
+
+        let handler = thread::spawn(move || {
+            if ev_tx.send(JobEvent::Started { total: Some(count) }).is_err() {
+                eprintln!("Main thread is dead");
+                return;
+            }
 
-        self.preload_thread_handler = Some(thread::spawn(move || {
-            let len = unwrap_am!(c_digits).len();
-            if len >= count {
-                let _ = res_tx.send(());
+            let mut journal = match journal_path.as_deref().map(Journal::open).transpose() {
+                Ok(journal) => journal,
+                Err(err) => {
+                    let _ = ev_tx.send(JobEvent::Warning(format!("Preload journal is disabled: {err}")));
+                    None
+                },
+            };
+
+            // skip whatever an interrupted preload has already downloaded
+            let (len, requests) = {
+                let mut digits = unwrap_am!(c_digits);
+                let mut pending = unwrap_am!(c_pending);
+                promote_chunks(&mut digits, &mut pending);
+
+                let missing = missing_ranges(digits.len()..count.max(digits.len()), &completed_ranges(&pending));
+                let requests: Vec<(usize, usize)> = missing.into_iter()
+                    .flat_map(|r| r.clone().step_by(digits_per_request).map(move |s| (s, digits_per_request.min(r.end - s))))
+                    .collect();
+                (digits.len(), requests)
+            };
+            let mut loaded = count.max(len) - requests.iter().map(|r| r.1).sum::<usize>();
+            let mut meter = ProgressMeter::new(loaded, Some(count));
+
+            if ev_tx.send(meter.event(loaded)).is_err() {
+                eprintln!("Main thread is dead");
                 return;
             }
-            let per_thread = (count - len) / num_of_threads;
-
-            // use only 1 thread
-            if per_thread < digits_per_request {
-                println!("Using 1 thread");
-
-                loop {
-                    let len = unwrap_am!(c_digits).len();
-    
-                    if loa_tx.send(len).is_err() {
-                        eprintln!("Main thread is dead");
-                        break;
-                    }
-                    if len >= count {
-                        let _ = res_tx.send(());
-                        break;
-                    }
-    
-                    let request_digits = digits_per_request.min(count - len);
-                    let new_digits = get_digits(&c_client.lock().unwrap(), len, request_digits).unwrap();
-                    
-                    unwrap_am!(c_digits).push_str(new_digits.as_str());
-                }
+            if requests.is_empty() {
+                let _ = ev_tx.send(JobEvent::Finished);
+                return;
             }
-            // use num_of_threads threads
-            else {
-                println!("Using {num_of_threads} threads");
-
-                let (tloa_tx, tloa_rx) = mpsc::channel();
-                let mut preload_threads_handlers = Vec::new();
-                let (res_txs, res_rxs) = mpsc::channel();
-    
-                let gstart = unwrap_am!(c_digits).len();
-                let gend = count;
-
-                for i in 0..num_of_threads {
-                    let tloa_tx = tloa_tx.clone();
-    
-                    let start = gstart + i * per_thread;
-                    let end;
-                    if i + 1 == num_of_threads {
-                        end = gend;
-                    }
-                    else {
-                        end = start + per_thread;
-                    }
 
-                    let c_client = c_client.clone();
-    
-                    let res_txs = res_txs.clone();
-                    preload_threads_handlers.push(thread::spawn(move || {
-                        let mut cur_loaded = start;
-                        let mut digits = String::default();
+            let num_of_threads = num_of_threads.min(requests.len());
+
+            let requests = Arc::new(requests);
+            let (w_tx, w_rx) = mpsc::channel();
+            let mut preload_threads_handlers = Vec::new();
+
+            for i in 0..num_of_threads {
+                let requests = requests.clone();
+                let (c_digits, c_pending, c_claims) = (c_digits.clone(), c_pending.clone(), c_claims.clone());
+                let c_client = c_client.clone();
+                let c_limiter = c_limiter.clone();
+                let c_config = c_config.clone();
+                let w_tx = w_tx.clone();
+                let ev_tx = ev_tx.clone();
+                preload_threads_handlers.push(thread::spawn(move || {
+                    // threads take turns, so chunks arrive roughly in order and can join the cache early
+                    for &(start, request_digits) in requests.iter().skip(i).step_by(num_of_threads) {
+                        // a search may be downloading part of the request already
+                        let mut fetched = 0usize;
                         loop {
-                            let request_digits = digits_per_request.min(end - cur_loaded);
-
-                            if request_digits == 0 {
+                            let claimed = c_claims.claim(start..start + request_digits, &c_digits, &c_pending);
+                            if claimed.is_empty() {
                                 break;
                             }
-
-                            digits.push_str(get_digits(&c_client.lock().unwrap(), cur_loaded, request_digits).unwrap().as_str());
-                            //println!("Thread {i}, request ({cur_loaded}-{}) completed", cur_loaded + request_digits);
-                            cur_loaded += request_digits;
-
-                            if tloa_tx.send(request_digits).is_err() {
-                                panic!("Main preload thread is dead");
+                            for claim in claimed {
+                                let start = claim.range.start;
+                                match fetch_digits(&c_client, &c_limiter, &c_config, start, claim.range.len(), &ev_tx) {
+                                    Ok((digits, bytes)) => {
+                                        fetched += digits.len();
+                                        store_chunk(&c_digits, &c_pending, start, digits.clone());
+                                        drop(claim);
+                                        if w_tx.send(WorkerMessage::Loaded { start, digits, bytes }).is_err() {
+                                            return;
+                                        }
+                                    },
+                                    Err(err) => {
+                                        let _ = w_tx.send(WorkerMessage::Failed(err));
+                                        return;
+                                    },
+                                }
                             }
                         }
-                        if res_txs.send((i, digits)).is_err() {
-                            panic!("Main preload thread is dead");
-                        }
-                    }));
-                }
-
-                let mut result_strs = vec![String::default(); num_of_threads];
-                let mut loa_len = unwrap_am!(c_digits).len();
-
-                while result_strs.contains(&String::default()) {
-                    loop {
-                        let tloa_res = tloa_rx.try_recv();
-                        match tloa_res {
-                            Ok(add_len) => loa_len += add_len,
-                            Err(err) => {
-                                match err {
-                                    mpsc::TryRecvError::Empty => break,
-                                    mpsc::TryRecvError::Disconnected => {},
-                                }
-                            },
+                        if fetched < request_digits && w_tx.send(WorkerMessage::Covered(request_digits - fetched)).is_err() {
+                            return;
                         }
                     }
-                    
-                    loop {
-                        let res_res = res_rxs.try_recv();
-                        match res_res {
-                            Ok((ind, s)) => result_strs[ind] = s,
-                            Err(err) => {
-                                match err {
-                                    mpsc::TryRecvError::Empty => break,
-                                    mpsc::TryRecvError::Disconnected => {},
-                                }
-                            },
+                }));
+            }
+            drop(w_tx);
+
+            // ends when all workers are done
+            for message in w_rx {
+                match message {
+                    WorkerMessage::Loaded { start, digits, bytes } => {
+                        if let Err(err) = journal.as_mut().map_or(Ok(()), |j| j.record(start, digits.as_str())) {
+                            let _ = ev_tx.send(JobEvent::Warning(format!("Preload journal is disabled: {err}")));
+                            journal = None;
                         }
-                    }
 
-                    if loa_tx.send(loa_len).is_err() {
-                        panic!("Main thread is dead");
-                    }
-                }
-
-                for rs in result_strs {
-                    unwrap_am!(c_digits).push_str(rs.as_str());
+                        loaded += digits.len();
+                        meter.add_bytes(bytes);
+                        if ev_tx.send(meter.event(loaded)).is_err() {
+                            eprintln!("Main thread is dead");
+                            return;
+                        }
+                    },
+                    WorkerMessage::Covered(n) => {
+                        loaded += n;
+                        if ev_tx.send(meter.event(loaded)).is_err() {
+                            eprintln!("Main thread is dead");
+                            return;
+                        }
+                    },
+                    WorkerMessage::Failed(err) => {
+                        let _ = ev_tx.send(JobEvent::Error(err));
+                        return;
+                    },
                 }
+            }
 
-                let _ = res_tx.send(());
+            for handler in preload_threads_handlers {
+                let _ = handler.join();
             }
-        }));
 
-        (loa_rx, res_rx)
+            let _ = ev_tx.send(JobEvent::Finished);
+        });
+        self.jobs.push((SearchState::Preloading, handler));
+
+        ev_rx
     }
 
-    pub fn search(&mut self, search_for: &str) -> (Receiver<usize>, Receiver<Option<usize>>) {
-        if self.get_state() != SearchState::Idle {
-            panic!("Can't search: state must be idle");
-        }
-        
-        let (pro_tx, pro_rx) = mpsc::channel();
-        let (res_tx, res_rx) = mpsc::channel();
+    pub fn search(&mut self, search_for: &str) -> Receiver<JobEvent> {
+        self.search_in(search_for, SearchBounds::default())
+    }
 
-        let c_digits = self.saved_digits.clone();
-        let c_client = self.client.clone();
-        let digits_per_request = self.digits_per_request;
+    // Continues after a match at `index`, through the cache first and then the network
+    pub fn find_next(&mut self, search_for: &str, index: usize) -> Receiver<JobEvent> {
+        self.search_in(search_for, SearchBounds::from(index + 1))
+    }
+
+    // Steps back from a match at `index`, only through the cache
+    pub fn find_previous(&self, search_for: &str, index: usize) -> Option<usize> {
+        find_previous(unwrap_am!(self.saved_digits).as_str(), search_for, index)
+    }
 
+    // Scans the cache, then the digits after it as they arrive, until a match or the end of `bounds`.
+    // While a preload runs, digits it's downloading are waited for, the others are fetched here and
+    // left in the cache for the preload.
+    pub fn search_in(&mut self, search_for: &str, bounds: SearchBounds) -> Receiver<JobEvent> {
+        let (ev_tx, ev_rx) = mpsc::channel();
+        let source = self.source();
         let search_for = search_for.to_string();
-        self.search_thread_handler = Some(thread::spawn(move || {
-            let ind = unwrap_am!(c_digits).find(search_for.as_str());
-            if ind.is_some() {
-                let _ = pro_tx.send(ind.unwrap());
-                let _ = res_tx.send(Some(ind.unwrap()));
+        let handler = thread::spawn(move || {
+            let started = Instant::now();
+            let scan_end = bounds.scan_end(search_for.len()).unwrap_or(usize::MAX);
+            if ev_tx.send(JobEvent::Started { total: bounds.scan_end(search_for.len()) }).is_err() {
+                eprintln!("Main thread is dead");
                 return;
             }
-            let mut digit = unwrap_am!(c_digits).len();
-            if pro_tx.send(digit).is_err() {
-                panic!("Main thread is dead");
-            }
-            
-            let mut last_digits: Option<String> = None;
-            loop {
-                let new_digits = get_digits(&c_client.lock().unwrap(), digit, digits_per_request).unwrap();
-                unwrap_am!(c_digits).push_str(new_digits.as_str());
-
-                let mut d: String = "".to_string();
-                if last_digits.is_some() {
-                    d.push_str(last_digits.as_ref().unwrap().as_str());
+
+            let mut meter = ProgressMeter::new(bounds.start, bounds.scan_end(search_for.len()));
+            let mut pos = bounds.start;
+            let mut carry = String::new(); // a match may start in the previous chunk
+            let outcome = loop {
+                if pos >= scan_end {
+                    break SearchOutcome::NotFound { searched: bounds.searched(pos, search_for.len()), reason: bounds.end_reason() };
+                }
+                if bounds.timed_out(started) {
+                    break SearchOutcome::NotFound { searched: bounds.searched(pos, search_for.len()), reason: StopReason::TimeLimit };
+                }
+
+                let chunk = match source.read(pos, SCAN_DIGITS.min(scan_end - pos), &mut meter, &ev_tx) {
+                    Ok(chunk) => chunk,
+                    Err(err) => {
+                        let _ = ev_tx.send(JobEvent::Error(err));
+                        return;
+                    },
+                };
+
+                let text = carry + chunk.as_str();
+                if let Some(ind) = text.find(search_for.as_str()) {
+                    let ind = pos + ind - (text.len() - chunk.len());
+                    let _ = ev_tx.send(meter.event(ind + search_for.len()));
+                    break SearchOutcome::Found(ind);
+                }
+                pos += chunk.len();
+                if ev_tx.send(meter.event(pos)).is_err() {
+                    eprintln!("Main thread is dead");
+                    return;
                 }
-                d.push_str(new_digits.as_str());
+                carry = text[text.len() - (search_for.len() - 1).min(text.len())..].to_string();
+            };
+
+            let _ = ev_tx.send(match outcome {
+                SearchOutcome::Found(ind) => JobEvent::Match(ind),
+                SearchOutcome::NotFound { searched, reason } => JobEvent::NotFound { searched, reason },
+            });
+            let _ = ev_tx.send(JobEvent::Finished);
+        });
+        self.jobs.push((SearchState::Searching, handler));
+        ev_rx
+    }
+
+    // Feeds the digits of `bounds` to the analyzer, from the cache and then the network like
+    // `search_in`. Without an end it runs until it's dropped.
+    pub fn analyze(&mut self, mut analyzer: Box<dyn Analyzer>, bounds: SearchBounds) -> Receiver<JobEvent> {
+        let (ev_tx, ev_rx) = mpsc::channel();
+        let source = self.source();
+
+        let handler = thread::spawn(move || {
+            let started = Instant::now();
+            let scan_end = bounds.limit().unwrap_or(usize::MAX);
+            if ev_tx.send(JobEvent::Started { total: bounds.limit() }).is_err() {
+                eprintln!("Main thread is dead");
+                return;
+            }
 
-                if pro_tx.send(digit + digits_per_request).is_err() {
-                    panic!("Main thread is dead");
+            let mut meter = ProgressMeter::new(bounds.start, bounds.limit());
+            let mut pos = bounds.start;
+            let mut findings = Vec::new();
+            let mut reported = 0usize;
+            while pos < scan_end {
+                if bounds.timed_out(started) {
+                    let _ = ev_tx.send(JobEvent::Warning(format!("Stopped by the time limit after {} digits", pos - bounds.start)));
+                    break;
+                }
+                let chunk = match source.read(pos, SCAN_DIGITS.min(scan_end - pos), &mut meter, &ev_tx) {
+                    Ok(chunk) => chunk,
+                    Err(err) => {
+                        let _ = ev_tx.send(JobEvent::Error(err));
+                        return;
+                    },
+                };
+                analyzer.feed(chunk.as_str(), pos, &mut findings);
+                pos += chunk.len();
+                if !report_findings(&mut findings, &mut reported, |event| ev_tx.send(event).is_ok()) || ev_tx.send(meter.event(pos)).is_err() {
+                    eprintln!("Main thread is dead");
+                    return;
                 }
-                
-                let ind = d.find(search_for.as_str());
-                if ind.is_some() {
-                    let _ = res_tx.send(Some(digit + ind.unwrap() - last_digits.as_ref().unwrap_or(&"".to_string()).len()));
+                if analyzer.done() {
                     break;
                 }
-                last_digits = Some(new_digits);
+            }
 
-                digit += digits_per_request;
+            analyzer.finish(pos, &mut findings);
+            report_findings(&mut findings, &mut reported, |event| ev_tx.send(event).is_ok());
+            if let Some(warning) = unreported_warning(reported) {
+                let _ = ev_tx.send(warning);
             }
-        }));
-        (pro_rx, res_rx)
+            let _ = ev_tx.send(JobEvent::Finished);
+        });
+        self.jobs.push((SearchState::Searching, handler));
+        ev_rx
     }
 
-    pub fn into_idle(&mut self) {
-        if self.preload_thread_handler.is_some() {
-            let _ = self.preload_thread_handler.take().unwrap().join();
+    fn source(&self) -> DigitSource {
+        DigitSource {
+            digits: self.saved_digits.clone(),
+            pending: self.pending_chunks.clone(),
+            claims: self.claims.clone(),
+            client: self.client.clone(),
+            limiter: self.limiter.clone(),
+            config: self.config.clone(),
+        }
+    }
+
+    // Joins the jobs that have ended, the others keep running
+    pub fn join_finished(&mut self) {
+        let (finished, running): (Vec<_>, Vec<_>) = self.jobs.drain(..).partition(|(_, handler)| handler.is_finished());
+        self.jobs = running;
+        for (_, handler) in finished {
+            let _ = handler.join();
         }
-        if self.search_thread_handler.is_some() {
-            let _ = self.search_thread_handler.take().unwrap().join();
+    }
+
+    // Waits for all jobs to end
+    pub fn into_idle(&mut self) {
+        for (_, handler) in self.jobs.drain(..) {
+            let _ = handler.join();
         }
     }
 }
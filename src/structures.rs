@@ -0,0 +1,237 @@
+use std::collections::BTreeSet;
+
+use crate::analysis::*;
+
+// Palindromes up to this long are all found. Longer ones need digits from before the window the
+// analyzer keeps, unless one ending right there is still growing.
+pub const MAX_PALINDROME: usize = 4096;
+
+// Which structures to look for. Each reports the first occurrence of every length.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+pub struct StructureOptions {
+    pub runs: bool,
+    pub ascending: bool,
+    pub descending: bool,
+    pub progressions: bool, // steps other than 0 and +-1, those are the three above
+    pub palindromes: bool,
+}
+
+impl Default for StructureOptions {
+    fn default() -> Self {
+        Self { runs: true, ascending: true, descending: true, progressions: true, palindromes: true }
+    }
+}
+
+impl StructureOptions {
+    pub fn analyzers(&self) -> Vec<Box<dyn Analyzer>> {
+        let mut analyzers: Vec<Box<dyn Analyzer>> = Vec::new();
+        if self.runs {
+            analyzers.push(Box::new(Progression::new(Step::Exactly(0))));
+        }
+        if self.ascending {
+            analyzers.push(Box::new(Progression::new(Step::Exactly(1))));
+        }
+        if self.descending {
+            analyzers.push(Box::new(Progression::new(Step::Exactly(-1))));
+        }
+        if self.progressions {
+            analyzers.push(Box::new(Progression::new(Step::Other)));
+        }
+        if self.palindromes {
+            analyzers.push(Box::new(Palindromes::new()));
+        }
+        analyzers
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    Exactly(i8),
+    Other, // any step but 0 and +-1
+}
+
+impl Step {
+    fn allows(self, step: i8) -> bool {
+        match self {
+            Step::Exactly(s) => s == step,
+            Step::Other => step.abs() >= 2,
+        }
+    }
+
+    fn name(self, step: i8) -> String {
+        match self {
+            Step::Exactly(0) => "run".to_string(),
+            Step::Exactly(1) => "ascending".to_string(),
+            Step::Exactly(-1) => "descending".to_string(),
+            _ => format!("step {step:+}"),
+        }
+    }
+}
+
+// Digits going up or down by the same step: runs of one digit (0), 123456 (1), 987654 (-1), 2468...
+// A progression of length n contains one of every shorter length, so reporting each new record
+// length as it's reached gives the first occurrence of every length.
+pub struct Progression {
+    step: Step,
+    last: Option<u8>,
+    current: Option<(usize, u8, i8, usize)>, // offset, first digit, step, length of the progression ending at `last`
+    longest: usize,
+}
+
+impl Progression {
+    pub fn new(step: Step) -> Self {
+        Self { step, last: None, current: None, longest: 1usize }
+    }
+}
+
+impl Analyzer for Progression {
+    fn feed(&mut self, chunk: &str, start: usize, findings: &mut Vec<Finding>) {
+        for (i, digit) in chunk.bytes().map(|b| b - b'0').enumerate() {
+            let offset = start + i;
+            let last = self.last.replace(digit);
+            let step = match last {
+                Some(last) => digit as i8 - last as i8,
+                None => continue,
+            };
+            if !self.step.allows(step) {
+                self.current = None;
+                continue;
+            }
+            let (position, first, step, len) = match self.current {
+                Some((position, first, s, len)) if s == step => (position, first, step, len + 1),
+                _ => (offset - 1, last.unwrap(), step, 2usize),
+            };
+            self.current = Some((position, first, step, len));
+            if len > self.longest {
+                self.longest = len;
+                findings.push(Finding {
+                    label: format!("{} of {len}", self.step.name(step)),
+                    position,
+                    digits: (0..len).map(|k| char::from(b'0' + (first as i8 + k as i8 * step) as u8)).collect(),
+                });
+            }
+        }
+    }
+}
+
+// Palindromes, the first one of every length. Keeps the lengths of the palindromes ending at the
+// last digit: each of them grows by two if the next digit mirrors the one before it.
+pub struct Palindromes {
+    recent: Vec<u8>, // the last digits, enough for the longest palindrome still growing
+    recent_start: usize,
+    lengths: Vec<usize>, // of the palindromes ending at the last digit, 1 included
+    found: BTreeSet<usize>,
+}
+
+impl Palindromes {
+    pub fn new() -> Self {
+        Self { recent: Vec::new(), recent_start: 0usize, lengths: Vec::new(), found: BTreeSet::new() }
+    }
+}
+
+impl Default for Palindromes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Analyzer for Palindromes {
+    fn feed(&mut self, chunk: &str, start: usize, findings: &mut Vec<Finding>) {
+        if self.recent.is_empty() {
+            self.recent_start = start;
+        }
+        for digit in chunk.bytes() {
+            self.recent.push(digit);
+            let end = self.recent.len() - 1; // index of the new digit
+            let mut lengths = vec![1usize];
+            if end >= 1 && self.recent[end - 1] == digit {
+                lengths.push(2);
+            }
+            for &len in &self.lengths {
+                if end > len && self.recent[end - len - 1] == digit {
+                    lengths.push(len + 2);
+                }
+            }
+
+            for &len in &lengths {
+                if len >= 2 && self.found.insert(len) {
+                    let first = end + 1 - len;
+                    findings.push(Finding {
+                        label: format!("palindrome of {len}"),
+                        position: self.recent_start + first,
+                        digits: String::from_utf8(self.recent[first..].to_vec()).unwrap(),
+                    });
+                }
+            }
+            self.lengths = lengths;
+
+            // a palindrome still to come may reach back as far as it's long
+            let keep = (self.lengths.iter().max().unwrap() + 1).max(MAX_PALINDROME);
+            if self.recent.len() > 2 * keep {
+                let drop = self.recent.len() - keep;
+                self.recent.drain(..drop);
+                self.recent_start += drop;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::tests::{positions, run};
+
+    #[test]
+    fn progressions() {
+        let mut ascending = Progression::new(Step::Exactly(1));
+        assert_eq!(positions(&run(&mut ascending, &["12", "34", "5"], 0)), [(0, "12"), (0, "123"), (0, "1234"), (0, "12345")]);
+
+        // only longer ones than before are reported
+        let mut runs = Progression::new(Step::Exactly(0));
+        let findings = run(&mut runs, &["3555", "1", "77", "8888"], 10);
+        assert_eq!(positions(&findings), [(11, "55"), (11, "555"), (17, "8888")]);
+        assert_eq!(findings[0].label, "run of 2");
+
+        let mut descending = Progression::new(Step::Exactly(-1));
+        assert_eq!(positions(&run(&mut descending, &["0987"], 0)), [(1, "98"), (1, "987")]);
+
+        let mut other = Progression::new(Step::Other);
+        let findings = run(&mut other, &["1357", "1", "963"], 0);
+        assert_eq!(positions(&findings), [(0, "13"), (0, "135"), (0, "1357")]);
+        assert_eq!(findings[2].label, "step +2 of 4");
+        let findings = run(&mut Progression::new(Step::Other), &["9630"], 0);
+        assert_eq!(findings.last().unwrap().label, "step -3 of 4");
+
+        assert!(run(&mut Progression::new(Step::Exactly(1)), &["", "7"], 0).is_empty());
+    }
+
+    #[test]
+    fn palindromes() {
+        assert_eq!(positions(&run(&mut Palindromes::new(), &["12321"], 0)), [(1, "232"), (0, "12321")]);
+        // across chunks, and from an offset
+        assert_eq!(positions(&run(&mut Palindromes::new(), &["12", "21"], 100)), [(101, "22"), (100, "1221")]);
+        // the first one of every length only
+        assert_eq!(positions(&run(&mut Palindromes::new(), &["11", "3", "44"], 0)), [(0, "11")]);
+        assert!(run(&mut Palindromes::new(), &["", "1234"], 0).is_empty());
+    }
+
+    #[test]
+    fn long_palindrome() {
+        // its center comes long after the digits it starts with
+        let half: String = (0..MAX_PALINDROME / 2).map(|i| char::from(b'0' + (i * 7 % 10) as u8)).collect();
+        let reversed: String = half.chars().rev().collect();
+        let filler = "0123456789".repeat(MAX_PALINDROME / 5);
+        let findings = run(&mut Palindromes::new(), &[&filler, &half, &reversed], 0);
+        let longest = findings.iter().max_by_key(|finding| finding.digits.len()).unwrap();
+        assert_eq!(longest.position, filler.len());
+        assert_eq!(longest.digits.len(), MAX_PALINDROME);
+    }
+
+    #[test]
+    fn options() {
+        assert_eq!(StructureOptions::default().analyzers().len(), 5);
+        let options = StructureOptions { runs: false, palindromes: false, ..StructureOptions::default() };
+        assert_eq!(options.analyzers().len(), 3);
+    }
+}
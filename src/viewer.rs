@@ -0,0 +1,156 @@
+use eframe::egui::{self, text::LayoutJob, Color32, TextFormat, TextStyle, Ui};
+use std::ops::Range;
+
+use crate::position::PositionConvention;
+
+const CURRENT_MATCH: Color32 = Color32::from_rgb(160, 110, 0);
+const OTHER_MATCH: Color32 = Color32::from_rgb(40, 80, 120);
+
+// Scrollable view of the cache in blocks of `group_size` digits, `groups_per_line` per line.
+// Only the visible lines are laid out, so the cache can be millions of digits long.
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "persistence", serde(default))]
+pub struct DigitViewer {
+    pub group_size: usize,
+    pub groups_per_line: usize,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    goto: String,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    scroll_to: Option<usize>, // line to bring to the top on the next frame
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    current: Option<Range<usize>>,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pattern: String, // other occurrences are highlighted too
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    error: Option<String>,
+}
+
+impl Default for DigitViewer {
+    fn default() -> Self {
+        Self {
+            group_size: 10,
+            groups_per_line: 5,
+            goto: String::new(),
+            scroll_to: None,
+            current: None,
+            pattern: String::new(),
+            error: None,
+        }
+    }
+}
+
+impl DigitViewer {
+    fn line_len(&self) -> usize {
+        self.group_size * self.groups_per_line
+    }
+
+    // Highlights a match and scrolls to it
+    pub fn show_match(&mut self, offset: usize, pattern: &str) {
+        self.current = Some(offset..offset + pattern.len());
+        self.pattern = pattern.to_string();
+        self.scroll_to = Some(offset / self.line_len());
+    }
+
+    pub fn go_to(&mut self, offset: usize) {
+        self.scroll_to = Some(offset / self.line_len());
+    }
+
+    pub fn show(&mut self, ui: &mut Ui, digits: &str, convention: PositionConvention, integer_digits: usize) {
+        ui.horizontal(|ui| {
+            ui.label("Group: ");
+            let group = ui.add(egui::DragValue::new(&mut self.group_size).clamp_range(1usize..=100usize));
+            ui.label("Groups per line: ");
+            let groups = ui.add(egui::DragValue::new(&mut self.groups_per_line).clamp_range(1usize..=20usize));
+            // keep the current match in view when the layout changes
+            if group.changed() || groups.changed() {
+                if let Some(current) = &self.current {
+                    self.scroll_to = Some(current.start / self.line_len());
+                }
+            }
+
+            ui.label("Go to: ");
+            ui.add(egui::TextEdit::singleline(&mut self.goto).desired_width(100f32));
+            if ui.button("Go").clicked() {
+                self.error = match convention.parse(self.goto.as_str(), integer_digits) {
+                    Ok(offset) if offset < digits.len() => {
+                        self.go_to(offset);
+                        None
+                    },
+                    Ok(_) => Some(format!("Only {} digits are loaded", digits.len())),
+                    Err(err) => Some(err),
+                };
+            }
+            if self.current.is_some() && ui.button("Clear highlight").clicked() {
+                self.current = None;
+                self.pattern.clear();
+            }
+        });
+        if let Some(err) = &self.error {
+            ui.colored_label(Color32::RED, err);
+        }
+
+        let line_len = self.line_len();
+        let lines = digits.len().div_ceil(line_len);
+        let row_height = ui.fonts().row_height(TextStyle::Monospace);
+        let ruler_width = convention.format(digits.len(), integer_digits).len().max(1);
+
+        let mut scroll_area = egui::ScrollArea::vertical().id_source("digit_viewer").auto_shrink([false, false]);
+        if let Some(line) = self.scroll_to.take() {
+            scroll_area = scroll_area.vertical_scroll_offset(line as f32 * (row_height + ui.spacing().item_spacing.y));
+        }
+        scroll_area.show_rows(ui, row_height, lines, |ui, rows| {
+            let start = rows.start * line_len;
+            let end = (rows.end * line_len).min(digits.len());
+            let others = self.find_visible(digits, start..end);
+
+            for line in rows {
+                let line_start = line * line_len;
+                let line_end = (line_start + line_len).min(digits.len());
+                let mut job = LayoutJob::default();
+
+                let ruler = format!("{:>ruler_width$}  ", convention.format(line_start, integer_digits));
+                job.append(ruler.as_str(), 0f32, TextFormat::simple(TextStyle::Monospace, Color32::GRAY));
+
+                for (i, digit) in digits[line_start..line_end].char_indices() {
+                    let offset = line_start + i;
+                    let background = if self.current.as_ref().is_some_and(|c| c.contains(&offset)) {
+                        CURRENT_MATCH
+                    }
+                    else if others.iter().any(|o| o.contains(&offset)) {
+                        OTHER_MATCH
+                    }
+                    else {
+                        Color32::TRANSPARENT
+                    };
+                    let leading_space = if i > 0 && i % self.group_size == 0 { row_height / 2f32 } else { 0f32 };
+                    let mut buf = [0u8; 4];
+                    job.append(digit.encode_utf8(&mut buf), leading_space, TextFormat {
+                        style: TextStyle::Monospace,
+                        color: ui.visuals().text_color(),
+                        background,
+                        ..Default::default()
+                    });
+                }
+                ui.label(job);
+            }
+        });
+    }
+
+    // Occurrences of the pattern overlapping `range`, found in the visible part only
+    fn find_visible(&self, digits: &str, range: Range<usize>) -> Vec<Range<usize>> {
+        let len = self.pattern.len();
+        if len == 0 {
+            return Vec::new();
+        }
+        let start = range.start.saturating_sub(len - 1);
+        let end = (range.end + len - 1).min(digits.len());
+        let window = &digits[start..end];
+        let mut found = Vec::new();
+        let mut from = 0;
+        while let Some(i) = window[from..].find(self.pattern.as_str()) {
+            found.push(start + from + i..start + from + i + len);
+            from += i + 1;
+        }
+        found
+    }
+}
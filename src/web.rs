@@ -0,0 +1,219 @@
+// Browser counterpart of `search::Search`: same interface, backed by `AsyncSearch` and the fetch API.
+// The digit cache is persisted in local storage and can be imported/exported as a file.
+
+use eframe::wasm_bindgen::{prelude::*, JsCast};
+use futures::StreamExt;
+use std::{cell::{Cell, RefCell}, rc::Rc, sync::{mpsc::{self, Receiver}, Arc, Mutex}};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+
+use crate::{analysis::Analyzer, async_search::AsyncSearch, config::SearchConfig, estimate::SearchEstimate, import::import_digits, job::{JobEvent, SearchBounds}, position::*};
+
+const CACHE_KEY: &str = "pi_search_digits";
+
+#[derive(PartialEq)]
+pub enum SearchState {
+    Idle,
+    Preloading,
+    Searching,
+}
+
+pub struct Search {
+    search: AsyncSearch,
+    preloads: Rc<Cell<usize>>, // running jobs
+    searches: Rc<Cell<usize>>,
+}
+
+impl Default for Search {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Like the threads of `search::Search`, the job stops once the receiver is dropped
+fn run_job<F>(running: Rc<Cell<usize>>, job: F, mut events: futures::channel::mpsc::UnboundedReceiver<JobEvent>) -> Receiver<JobEvent>
+where
+    F: std::future::Future + 'static,
+{
+    let (ev_tx, ev_rx) = mpsc::channel();
+    let (job, abort) = futures::future::abortable(job);
+    running.set(running.get() + 1);
+    spawn_local(async move {
+        let _ = job.await;
+        running.set(running.get() - 1);
+    });
+    spawn_local(async move {
+        while let Some(event) = events.next().await {
+            if ev_tx.send(event).is_err() {
+                abort.abort();
+                break;
+            }
+        }
+    });
+    ev_rx
+}
+
+impl Search {
+    pub fn new() -> Self {
+        Self {
+            search: AsyncSearch::new(),
+            preloads: Rc::default(),
+            searches: Rc::default(),
+        }
+    }
+
+    pub fn get_state(&self) -> SearchState {
+        if self.preloads.get() > 0 {
+            SearchState::Preloading
+        }
+        else if self.searches.get() > 0 {
+            SearchState::Searching
+        }
+        else {
+            SearchState::Idle
+        }
+    }
+
+    pub fn get_config(&self) -> &SearchConfig {
+        self.search.get_config()
+    }
+
+    pub fn set_config(&mut self, config: SearchConfig) -> Result<(), Box<dyn std::error::Error>> {
+        self.search.set_config(config)
+    }
+
+    pub fn set_digits_per_request(&mut self, digits_per_request: usize) {
+        self.search.set_digits_per_request(digits_per_request);
+    }
+
+    pub fn get_digits(&self) -> Arc<Mutex<String>> {
+        self.search.get_digits()
+    }
+    pub fn digits_loaded(&self) -> usize {
+        self.search.digits_loaded()
+    }
+    pub fn integer_digits(&self) -> usize {
+        PI_INTEGER_DIGITS
+    }
+
+    // Expected position of the pattern and the chances to find it in the cache or the bounds.
+    // `rate` is the download speed in digits/s, for the ETA.
+    pub fn estimate(&self, search_for: &str, bounds: &SearchBounds, rate: Option<f64>) -> SearchEstimate {
+        self.search.estimate(search_for, bounds, rate)
+    }
+
+    // The browser keeps up to max_in_flight requests going instead of running worker threads
+    pub fn preload(&mut self, count: usize) -> Receiver<JobEvent> {
+        let (job, events) = self.search.preload(count);
+        run_job(self.preloads.clone(), job, events)
+    }
+
+    pub fn search(&mut self, search_for: &str) -> Receiver<JobEvent> {
+        self.search_in(search_for, SearchBounds::default())
+    }
+
+    pub fn analyze(&mut self, analyzer: Box<dyn Analyzer>, bounds: SearchBounds) -> Receiver<JobEvent> {
+        let (job, events) = self.search.analyze(analyzer, bounds);
+        run_job(self.searches.clone(), job, events)
+    }
+
+    pub fn find_next(&mut self, search_for: &str, index: usize) -> Receiver<JobEvent> {
+        self.search_in(search_for, SearchBounds::from(index + 1))
+    }
+
+    pub fn find_previous(&self, search_for: &str, index: usize) -> Option<usize> {
+        self.search.find_previous(search_for, index)
+    }
+
+    pub fn search_in(&mut self, search_for: &str, bounds: SearchBounds) -> Receiver<JobEvent> {
+        let (job, events) = self.search.search_in(search_for, bounds);
+        run_job(self.searches.clone(), job, events)
+    }
+
+    // Jobs finish on their own in the browser, there is nothing to join
+    pub fn into_idle(&mut self) {}
+    pub fn join_finished(&mut self) {}
+}
+
+fn local_storage() -> Result<web_sys::Storage, JsValue> {
+    web_sys::window()
+        .ok_or("No window")?
+        .local_storage()?
+        .ok_or_else(|| "Local storage is not available".into())
+}
+
+pub fn load_cache() -> Result<Option<String>, JsValue> {
+    local_storage()?.get_item(CACHE_KEY)
+}
+
+// Browsers give a site about 5 MB of local storage, larger caches don't fit
+pub fn save_cache(digits: &str) -> Result<(), String> {
+    let storage = local_storage().map_err(|err| format!("The cache wasn't saved, the browser storage is unavailable: {err:?}"))?;
+    storage.set_item(CACHE_KEY, digits).map_err(|err| {
+        let name = js_sys::Reflect::get(&err, &JsValue::from_str("name")).ok().and_then(|name| name.as_string());
+        if name.as_deref() == Some("QuotaExceededError") {
+            format!("The cache wasn't saved, {} digits are more than the browser storage holds. Use Export file to keep them.", digits.len())
+        }
+        else {
+            format!("The cache wasn't saved: {err:?}")
+        }
+    })
+}
+
+// Offers the text as a file download
+pub fn download_file(file_name: &str, text: &str) -> Result<(), JsValue> {
+    download_bytes(file_name, text.as_bytes(), "text/plain")
+}
+
+pub fn download_bytes(file_name: &str, bytes: &[u8], mime_type: &str) -> Result<(), JsValue> {
+    let document = web_sys::window().ok_or("No window")?.document().ok_or("No document")?;
+
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, web_sys::BlobPropertyBag::new().type_(mime_type))?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+
+    let anchor: web_sys::HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+    anchor.set_href(url.as_str());
+    anchor.set_download(file_name);
+    anchor.click();
+
+    web_sys::Url::revoke_object_url(url.as_str())
+}
+
+// Lets the user pick a file; its digits replace the cache once it's read.
+// The outcome goes to `message`, the file is read after this returns.
+pub fn import_cache(digits: Arc<Mutex<String>>, message: Rc<RefCell<Option<Result<String, String>>>>) -> Result<(), JsValue> {
+    let document = web_sys::window().ok_or("No window")?.document().ok_or("No document")?;
+
+    let input: web_sys::HtmlInputElement = document.create_element("input")?.dyn_into()?;
+    input.set_type("file");
+    input.set_accept(".txt,.ycd,text/plain");
+
+    let c_input = input.clone();
+    let on_change = Closure::once(move || {
+        let file = match c_input.files().and_then(|files| files.get(0)) {
+            Some(file) => file,
+            None => return,
+        };
+        spawn_local(async move {
+            let res = match JsFuture::from(file.array_buffer()).await {
+                Ok(buffer) => match import_digits(&js_sys::Uint8Array::new(&buffer).to_vec(), true) {
+                    Ok((_, report)) if report.integer_digits.is_some_and(|n| n != PI_INTEGER_DIGITS) => {
+                        Err(format!("Cache has {} digits before the decimal point", report.integer_digits.unwrap()))
+                    },
+                    Ok((file_digits, report)) => {
+                        *digits.lock().unwrap() = file_digits;
+                        Ok(report.to_string())
+                    },
+                    Err(err) => Err(err),
+                },
+                Err(err) => Err(format!("Error while reading {}: {err:?}", file.name())),
+            };
+            *message.borrow_mut() = Some(res);
+        });
+    });
+    input.set_onchange(Some(on_change.as_ref().unchecked_ref()));
+    on_change.forget();
+
+    input.click();
+    Ok(())
+}
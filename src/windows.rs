@@ -0,0 +1,261 @@
+use std::collections::VecDeque;
+
+use crate::analysis::*;
+
+pub const MAX_PRIME_DIGITS: usize = 19; // fits in a u64
+
+// What the number formed by a window of digits has to satisfy
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+pub enum WindowCondition {
+    DigitSum(u32),
+    Prime, // a k-digit prime, so no leading zero
+    DivisibleBy(u64),
+}
+
+impl WindowCondition {
+    pub fn name(self) -> &'static str {
+        match self {
+            WindowCondition::DigitSum(_) => "digit sum",
+            WindowCondition::Prime => "prime",
+            WindowCondition::DivisibleBy(_) => "divisible by",
+        }
+    }
+}
+
+// The first window of `digits` digits whose number meets the condition
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+pub struct WindowQuery {
+    pub digits: usize,
+    pub condition: WindowCondition,
+}
+
+impl Default for WindowQuery {
+    fn default() -> Self {
+        Self { digits: 5, condition: WindowCondition::Prime }
+    }
+}
+
+impl WindowQuery {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.digits == 0 {
+            return Err("A window needs at least one digit".to_string());
+        }
+        match self.condition {
+            WindowCondition::DigitSum(sum) if sum as usize > 9 * self.digits => Err(format!("{} digits can't sum to more than {}", self.digits, 9 * self.digits)),
+            WindowCondition::Prime if self.digits > MAX_PRIME_DIGITS => Err(format!("Primes can have at most {MAX_PRIME_DIGITS} digits")),
+            WindowCondition::DivisibleBy(0) => Err("Can't divide by 0".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self.condition {
+            WindowCondition::DigitSum(sum) => format!("{} digits summing to {sum}", self.digits),
+            WindowCondition::Prime => format!("{}-digit prime", self.digits),
+            WindowCondition::DivisibleBy(m) => format!("{} digits divisible by {m}", self.digits),
+        }
+    }
+}
+
+// Slides the window along the stream, keeping the digit sum and the remainder up to date instead
+// of recomputing them for every window
+pub struct WindowSearch {
+    query: WindowQuery,
+    window: VecDeque<u8>,
+    start: usize, // offset of the window
+    sum: u32,
+    remainder: u64,      // of the window's number, for DivisibleBy
+    leading_weight: u64, // 10^(digits - 1) mod m
+    found: bool,
+}
+
+impl WindowSearch {
+    pub fn new(query: WindowQuery) -> Self {
+        let leading_weight = match query.condition {
+            WindowCondition::DivisibleBy(m) => (1..query.digits).fold(1u64 % m, |w, _| mul_mod(w, 10, m)),
+            _ => 0u64,
+        };
+        Self { query, window: VecDeque::new(), start: 0usize, sum: 0u32, remainder: 0u64, leading_weight, found: false }
+    }
+
+    fn matches(&self) -> bool {
+        match self.query.condition {
+            WindowCondition::DigitSum(sum) => self.sum == sum,
+            WindowCondition::Prime => {
+                self.window[0] != 0 && is_prime(self.window.iter().fold(0u64, |n, &d| n * 10 + d as u64))
+            },
+            WindowCondition::DivisibleBy(_) => self.remainder == 0,
+        }
+    }
+}
+
+impl Analyzer for WindowSearch {
+    fn feed(&mut self, chunk: &str, start: usize, findings: &mut Vec<Finding>) {
+        if self.window.is_empty() {
+            self.start = start;
+        }
+        for digit in chunk.bytes().map(|b| b - b'0') {
+            if self.found {
+                return;
+            }
+            if self.window.len() == self.query.digits {
+                let out = self.window.pop_front().unwrap();
+                self.start += 1;
+                self.sum -= out as u32;
+                if let WindowCondition::DivisibleBy(m) = self.query.condition {
+                    let out = mul_mod(out as u64, self.leading_weight, m);
+                    self.remainder = if self.remainder >= out { self.remainder - out } else { self.remainder + (m - out) };
+                }
+            }
+            self.window.push_back(digit);
+            self.sum += digit as u32;
+            if let WindowCondition::DivisibleBy(m) = self.query.condition {
+                self.remainder = ((self.remainder as u128 * 10 + digit as u128) % m as u128) as u64;
+            }
+
+            if self.window.len() == self.query.digits && self.matches() {
+                self.found = true;
+                findings.push(Finding {
+                    label: self.query.describe(),
+                    position: self.start,
+                    digits: self.window.iter().map(|&d| char::from(b'0' + d)).collect(),
+                });
+            }
+        }
+    }
+
+    fn done(&self) -> bool {
+        self.found
+    }
+}
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result = 1u64 % m;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+// Miller-Rabin with the bases that are enough for every u64
+pub fn is_prime(n: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    for p in BASES {
+        if n % p == 0 {
+            return n == p;
+        }
+    }
+    let mut d = n - 1;
+    let mut s = 0;
+    while d % 2 == 0 {
+        d /= 2;
+        s += 1;
+    }
+    'bases: for a in BASES {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'bases;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::tests::{positions, run};
+
+    const PI: &str = "31415926535897932384626433832795028841971693993751";
+
+    fn first(digits: usize, condition: WindowCondition, chunks: &[&str]) -> Vec<(usize, String)> {
+        let findings = run(&mut WindowSearch::new(WindowQuery { digits, condition }), chunks, 0);
+        positions(&findings).into_iter().map(|(p, d)| (p, d.to_string())).collect()
+    }
+
+    // The first window meeting the condition, the slow way
+    fn brute_force(digits: &str, len: usize, condition: WindowCondition) -> Option<usize> {
+        (0..(digits.len() + 1).saturating_sub(len)).find(|&i| {
+            let window = &digits[i..i + len];
+            let n: u128 = window.parse().unwrap();
+            match condition {
+                WindowCondition::DigitSum(sum) => window.bytes().map(|b| (b - b'0') as u32).sum::<u32>() == sum,
+                WindowCondition::Prime => !window.starts_with('0') && is_prime(n as u64),
+                WindowCondition::DivisibleBy(m) => n % m as u128 == 0,
+            }
+        })
+    }
+
+    #[test]
+    fn primes() {
+        let primes: Vec<u64> = (0..60).filter(|&n| is_prime(n)).collect();
+        assert_eq!(primes, [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59]);
+        assert!(is_prime(1_000_000_007));
+        assert!(!is_prime(1_000_000_007 * 3));
+        assert!(!is_prime(3_215_031_751)); // a strong pseudoprime to bases 2, 3, 5 and 7
+        assert!(is_prime(18_446_744_073_709_551_557)); // the largest u64 prime
+        assert!(!is_prime(u64::MAX));
+    }
+
+    #[test]
+    fn first_windows() {
+        for (len, condition) in [
+            (1, WindowCondition::Prime), (2, WindowCondition::Prime), (5, WindowCondition::Prime),
+            (3, WindowCondition::DigitSum(20)), (4, WindowCondition::DigitSum(1)),
+            (3, WindowCondition::DivisibleBy(7)), (6, WindowCondition::DivisibleBy(1001)), (19, WindowCondition::DivisibleBy(u64::MAX)),
+        ] {
+            let expected = brute_force(PI, len, condition);
+            let found = first(len, condition, &[&PI[..17], &PI[17..20], &PI[20..]]);
+            assert_eq!(found.first().map(|(p, _)| *p), expected, "{len} digits, {condition:?}");
+            if let Some(p) = expected {
+                assert_eq!(found, [(p, PI[p..p + len].to_string())]);
+            }
+        }
+    }
+
+    #[test]
+    fn edges() {
+        // at the start, at the very end, and longer than the digits
+        assert_eq!(first(2, WindowCondition::DigitSum(4), &["3141"]), [(0, "31".to_string())]);
+        assert_eq!(first(2, WindowCondition::DigitSum(17), &["31", "489"]), [(3, "89".to_string())]);
+        assert!(first(5, WindowCondition::DivisibleBy(1), &["3141"]).is_empty());
+        assert!(first(1, WindowCondition::DivisibleBy(1), &[""]).is_empty());
+        // no leading zeros, 02 isn't a 2-digit prime
+        assert_eq!(first(2, WindowCondition::Prime, &["0241"]), [(2, "41".to_string())]);
+
+        let mut search = WindowSearch::new(WindowQuery { digits: 1, condition: WindowCondition::DigitSum(1) });
+        assert!(!search.done());
+        run(&mut search, &["3141"], 0);
+        assert!(search.done());
+    }
+
+    #[test]
+    fn validate() {
+        assert!(WindowQuery::default().validate().is_ok());
+        assert!(WindowQuery { digits: 0, condition: WindowCondition::Prime }.validate().is_err());
+        assert!(WindowQuery { digits: MAX_PRIME_DIGITS + 1, condition: WindowCondition::Prime }.validate().is_err());
+        assert!(WindowQuery { digits: 2, condition: WindowCondition::DigitSum(19) }.validate().is_err());
+        assert!(WindowQuery { digits: 2, condition: WindowCondition::DigitSum(18) }.validate().is_ok());
+        assert!(WindowQuery { digits: 2, condition: WindowCondition::DivisibleBy(0) }.validate().is_err());
+    }
+}